@@ -0,0 +1,806 @@
+//! glTF 2.0 binary (.glb) models: node hierarchy, triangle meshes and base colors of materials.
+//! Textures, animations, skins and morph targets are ignored,
+//! files which require any extension are rejected
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::json::Json;
+use super::FormatError;
+use crate::geometry::color::Color;
+use crate::geometry::g2d::Point2;
+use crate::geometry::g3d::{Point3, Triagnle3, Vector3};
+use crate::geometry::{Mesh, Shape};
+
+const MAGIC: &[u8] = b"glTF";
+const HEADER_LEN: usize = 12;
+const CHUNK_HEADER_LEN: usize = 8;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+/// One primitive of a mesh placed by its node, in the world coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMesh {
+    /// Name of the node, or of the mesh if the node has no name
+    pub name: String,
+    pub mesh: Mesh,
+    /// Base color factor of the material, vertex colors are already multiplied by it
+    pub color: Color,
+}
+
+/// All the meshes of the default scene flattened into a list.
+/// Front faces of glTF go counter clockwise, so the order of vertices of triangles
+/// is reversed to make `Triagnle3::normal` point outside
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfModel {
+    pub meshes: Vec<GltfMesh>,
+}
+
+impl GltfModel {
+    pub fn parse_glb(bytes: &[u8]) -> Result<Self, FormatError> {
+        let (json, bin) = chunks(bytes)?;
+        let document = Document { json: &json, bin };
+        document.check_support()?;
+
+        let mut meshes = Vec::new();
+        let mut visited = HashSet::new();
+        for node in document.root_nodes()? {
+            document.visit(node, &Matrix::identity(), &mut visited, &mut meshes)?;
+        }
+        Ok(GltfModel { meshes })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        GltfModel::parse_glb(&fs::read(path)?)
+    }
+}
+
+impl Shape for GltfModel {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.meshes
+            .iter()
+            .flat_map(|m| m.mesh.approximate())
+            .collect()
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Parsed JSON chunk and the binary chunk if there is one
+fn chunks(bytes: &[u8]) -> Result<(Json, Option<&[u8]>), FormatError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(FormatError::binary(bytes.len(), "header is too short"));
+    }
+    if &bytes[..4] != MAGIC {
+        return Err(FormatError::binary(0, "not a glTF binary file"));
+    }
+    let version = read_u32(bytes, 4);
+    if version != 2 {
+        return Err(FormatError::Unsupported(format!(
+            "glTF version {}",
+            version
+        )));
+    }
+    let length = read_u32(bytes, 8) as usize;
+    if bytes.len() < length {
+        return Err(FormatError::binary(
+            bytes.len(),
+            format!("file needs {} bytes", length),
+        ));
+    }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = HEADER_LEN;
+    while offset < length {
+        if offset + CHUNK_HEADER_LEN > length {
+            return Err(FormatError::binary(offset, "chunk header is too short"));
+        }
+        let chunk_length = read_u32(bytes, offset) as usize;
+        let chunk_type = read_u32(bytes, offset + 4);
+        let start = offset + CHUNK_HEADER_LEN;
+        // the length is read from the file and usize has 32 bits in wasm
+        let end = start
+            .checked_add(chunk_length)
+            .ok_or_else(|| FormatError::Invalid(format!("chunk at {} is too long", offset)))?;
+        if end > length {
+            return Err(FormatError::binary(
+                offset,
+                format!("chunk needs {} bytes", chunk_length),
+            ));
+        }
+        let data = &bytes[start..end];
+        match chunk_type {
+            CHUNK_JSON if offset == HEADER_LEN => {
+                let text = std::str::from_utf8(data).map_err(|e| {
+                    FormatError::binary(start + e.valid_up_to(), "JSON chunk is not valid text")
+                })?;
+                json = Some(Json::parse(text)?);
+            }
+            CHUNK_BIN if offset > HEADER_LEN && bin.is_none() => bin = Some(data),
+            // chunks of unknown types have to be ignored
+            _ if offset > HEADER_LEN => {}
+            _ => return Err(FormatError::binary(offset, "first chunk has to be JSON")),
+        }
+        offset = end;
+    }
+    let json = json.ok_or_else(|| FormatError::binary(HEADER_LEN, "no JSON chunk"))?;
+    Ok((json, bin))
+}
+
+/// Affine transform, 4x4 matrix in column major order like in glTF
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Matrix([f64; 16]);
+
+impl Matrix {
+    fn identity() -> Self {
+        let mut m = [0.; 16];
+        for i in 0..4 {
+            m[i * 5] = 1.;
+        }
+        Matrix(m)
+    }
+
+    fn at(&self, row: usize, col: usize) -> f64 {
+        self.0[col * 4 + row]
+    }
+
+    /// Translation, rotation by the quaternion (x, y, z, w) and scale applied in reverse order
+    fn from_trs(t: &[f64], r: &[f64], s: &[f64]) -> Self {
+        let (x, y, z, w) = (r[0], r[1], r[2], r[3]);
+        let rotation = [
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - z * w),
+                2. * (x * z + y * w),
+            ],
+            [
+                2. * (x * y + z * w),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - x * w),
+            ],
+            [
+                2. * (x * z - y * w),
+                2. * (y * z + x * w),
+                1. - 2. * (x * x + y * y),
+            ],
+        ];
+        let mut m = Matrix::identity().0;
+        for col in 0..3 {
+            for row in 0..3 {
+                m[col * 4 + row] = rotation[row][col] * s[col];
+            }
+            m[12 + col] = t[col];
+        }
+        Matrix(m)
+    }
+
+    fn mul(&self, other: &Matrix) -> Matrix {
+        let mut m = [0.; 16];
+        for col in 0..4 {
+            for row in 0..4 {
+                m[col * 4 + row] = (0..4).map(|k| self.at(row, k) * other.at(k, col)).sum();
+            }
+        }
+        Matrix(m)
+    }
+
+    fn point(&self, p: &Point3) -> Point3 {
+        let c = |row| self.at(row, 0) * p.x + self.at(row, 1) * p.y + self.at(row, 2) * p.z;
+        Point3::new(
+            c(0) + self.at(0, 3),
+            c(1) + self.at(1, 3),
+            c(2) + self.at(2, 3),
+        )
+    }
+
+    fn minor(&self, row: usize, col: usize) -> f64 {
+        let rows: Vec<usize> = (0..3).filter(|&r| r != row).collect();
+        let cols: Vec<usize> = (0..3).filter(|&c| c != col).collect();
+        self.at(rows[0], cols[0]) * self.at(rows[1], cols[1])
+            - self.at(rows[0], cols[1]) * self.at(rows[1], cols[0])
+    }
+
+    fn determinant(&self) -> f64 {
+        (0..3)
+            .map(|col| (-1_f64).powi(col as i32) * self.at(0, col) * self.minor(0, col))
+            .sum()
+    }
+
+    /// Normals are transformed by the inverse transposed matrix,
+    /// which is the matrix of cofactors up to a scale
+    fn normal(&self, n: &Vector3) -> Vector3 {
+        let cofactor =
+            |row: usize, col: usize| (-1_f64).powi((row + col) as i32) * self.minor(row, col);
+        let c = |row| cofactor(row, 0) * n.x + cofactor(row, 1) * n.y + cofactor(row, 2) * n.z;
+        let normal = Vector3::new(c(0), c(1), c(2)).scale(self.determinant().signum());
+        if normal.length() > 0. {
+            normal.normalize()
+        } else {
+            normal
+        }
+    }
+}
+
+/// Size in bytes of a component of accessors
+fn component_size(component_type: usize) -> Option<usize> {
+    match component_type {
+        5120 | 5121 => Some(1),
+        5122 | 5123 => Some(2),
+        5125 | 5126 => Some(4),
+        _ => None,
+    }
+}
+
+fn components(accessor_type: &str) -> Option<usize> {
+    match accessor_type {
+        "SCALAR" => Some(1),
+        "VEC2" => Some(2),
+        "VEC3" => Some(3),
+        "VEC4" => Some(4),
+        "MAT2" => Some(4),
+        "MAT3" => Some(9),
+        "MAT4" => Some(16),
+        _ => None,
+    }
+}
+
+/// Value of the component, normalized integers are mapped to [0, 1] or [-1, 1]
+fn component(bytes: &[u8], component_type: usize, normalized: bool) -> f64 {
+    let (value, max) = match component_type {
+        5120 => (bytes[0] as i8 as f64, 127.),
+        5121 => (bytes[0] as f64, 255.),
+        5122 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f64, 32767.),
+        5123 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f64, 65535.),
+        5125 => (read_u32(bytes, 0) as f64, u32::MAX as f64),
+        _ => return f32::from_bits(read_u32(bytes, 0)) as f64,
+    };
+    if normalized {
+        (value / max).max(-1.)
+    } else {
+        value
+    }
+}
+
+fn channel(value: f64) -> u8 {
+    (value * 255.).round().clamp(0., 255.) as u8
+}
+
+struct Document<'a> {
+    json: &'a Json,
+    bin: Option<&'a [u8]>,
+}
+
+impl<'a> Document<'a> {
+    fn check_support(&self) -> Result<(), FormatError> {
+        let version = self
+            .json
+            .get("asset")
+            .and_then(|a| a.get("version"))
+            .and_then(Json::as_str)
+            .ok_or_else(|| FormatError::Invalid("no `asset.version`".to_string()))?;
+        if !version.starts_with("2.") {
+            return Err(FormatError::Unsupported(format!(
+                "glTF version {}",
+                version
+            )));
+        }
+        // optional extensions have a fallback, the required ones can't be ignored
+        if let Some(required) = self.json.get("extensionsRequired").and_then(Json::as_array) {
+            if let Some(name) = required.first() {
+                return Err(FormatError::Unsupported(format!(
+                    "extension `{}`",
+                    name.as_str().unwrap_or_default()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Object number `idx` of the top level array, like `meshes`
+    fn item(&self, array: &str, idx: usize) -> Result<&'a Json, FormatError> {
+        self.json
+            .get(array)
+            .and_then(Json::as_array)
+            .and_then(|a| a.get(idx))
+            .ok_or_else(|| FormatError::Invalid(format!("no {} {}", array, idx)))
+    }
+
+    /// Nodes of the default scene, or all the nodes which are not children if there are no scenes
+    fn root_nodes(&self) -> Result<Vec<usize>, FormatError> {
+        let scene = self.json.get("scene").and_then(Json::as_usize).unwrap_or(0);
+        if self.json.get("scenes").is_some() {
+            return indices(self.item("scenes", scene)?, "nodes");
+        }
+        let nodes = self
+            .json
+            .get("nodes")
+            .and_then(Json::as_array)
+            .unwrap_or_default();
+        let mut children = Vec::new();
+        for node in nodes.iter() {
+            children.extend(indices(node, "children")?);
+        }
+        Ok((0..nodes.len()).filter(|i| !children.contains(i)).collect())
+    }
+
+    /// Adds meshes of the node and of its children, `visited` are the nodes added before.
+    /// Nodes form a forest, so reaching a node twice means a cycle or a shared child
+    fn visit(
+        &self,
+        idx: usize,
+        parent: &Matrix,
+        visited: &mut HashSet<usize>,
+        meshes: &mut Vec<GltfMesh>,
+    ) -> Result<(), FormatError> {
+        if !visited.insert(idx) {
+            return Err(FormatError::Invalid(format!(
+                "node {} is reached twice",
+                idx
+            )));
+        }
+        let node = self.item("nodes", idx)?;
+        let transform = parent.mul(&node_matrix(node, idx)?);
+
+        if let Some(mesh_idx) = node.get("mesh").and_then(Json::as_usize) {
+            let mesh = self.item("meshes", mesh_idx)?;
+            let name = node
+                .get("name")
+                .or_else(|| mesh.get("name"))
+                .and_then(Json::as_str)
+                .map_or_else(|| format!("mesh {}", mesh_idx), str::to_string);
+            let primitives = mesh
+                .get("primitives")
+                .and_then(Json::as_array)
+                .unwrap_or_default();
+            for primitive in primitives.iter() {
+                if let Some((mesh, color)) = self.primitive(primitive, &transform)? {
+                    meshes.push(GltfMesh {
+                        name: name.clone(),
+                        mesh,
+                        color,
+                    });
+                }
+            }
+        }
+
+        for child in indices(node, "children")? {
+            self.visit(child, &transform, visited, meshes)?;
+        }
+        Ok(())
+    }
+
+    /// Triangles of the primitive moved by the transform and its base color,
+    /// None for points and lines
+    fn primitive(
+        &self,
+        primitive: &Json,
+        transform: &Matrix,
+    ) -> Result<Option<(Mesh, Color)>, FormatError> {
+        let mode = primitive.get("mode").and_then(Json::as_usize).unwrap_or(4);
+        if mode < 4 {
+            return Ok(None);
+        }
+        let attributes = primitive
+            .get("attributes")
+            .ok_or_else(|| FormatError::Invalid("primitive without attributes".to_string()))?;
+        let attribute = |name: &str| attributes.get(name).and_then(Json::as_usize);
+
+        let position = attribute("POSITION")
+            .ok_or_else(|| FormatError::Invalid("primitive without `POSITION`".to_string()))?;
+        let positions: Vec<Point3> = self
+            .accessor(position, 3)?
+            .iter()
+            .map(|v| transform.point(&Point3::new(v[0], v[1], v[2])))
+            .collect();
+
+        let vertices: Vec<usize> = match primitive.get("indices").and_then(Json::as_usize) {
+            Some(idx) => self
+                .accessor(idx, 1)?
+                .iter()
+                .map(|v| v[0] as usize)
+                .collect(),
+            None => (0..positions.len()).collect(),
+        };
+        let mut triangles: Vec<[usize; 3]> = match mode {
+            4 => vertices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+            5 => (0..vertices.len().saturating_sub(2))
+                .map(|i| {
+                    let t = &vertices[i..i + 3];
+                    if i % 2 == 0 {
+                        [t[0], t[1], t[2]]
+                    } else {
+                        [t[0], t[2], t[1]]
+                    }
+                })
+                .collect(),
+            6 => (1..vertices.len().saturating_sub(1))
+                .map(|i| [vertices[0], vertices[i], vertices[i + 1]])
+                .collect(),
+            _ => return Err(FormatError::Invalid(format!("primitive mode {}", mode))),
+        };
+        // mirroring transforms turn counter clockwise faces into clockwise ones
+        if transform.determinant() > 0. {
+            for t in triangles.iter_mut() {
+                t.swap(1, 2);
+            }
+        }
+
+        let invalid = |what: &str| FormatError::Invalid(format!("{} don't match positions", what));
+        let mut mesh = Mesh::new(positions, triangles)
+            .ok_or_else(|| FormatError::Invalid("index is out of positions".to_string()))?;
+        if let Some(idx) = attribute("NORMAL") {
+            let normals = self
+                .accessor(idx, 3)?
+                .iter()
+                .map(|v| transform.normal(&Vector3::new(v[0], v[1], v[2])))
+                .collect();
+            mesh = mesh
+                .with_normals(normals)
+                .ok_or_else(|| invalid("normals"))?;
+        }
+        if let Some(idx) = attribute("TEXCOORD_0") {
+            let uvs = self
+                .accessor(idx, 2)?
+                .iter()
+                .map(|v| Point2::new(v[0], v[1]))
+                .collect();
+            mesh = mesh
+                .with_uvs(uvs)
+                .ok_or_else(|| invalid("texture coordinates"))?;
+        }
+
+        let factor = self.base_color(primitive)?;
+        if let Some(idx) = attribute("COLOR_0") {
+            let colors = self
+                .accessor(idx, 3)?
+                .iter()
+                .map(|v| {
+                    let alpha = v.get(3).cloned().unwrap_or(1.);
+                    Color::new(
+                        channel(v[0] * factor[0]),
+                        channel(v[1] * factor[1]),
+                        channel(v[2] * factor[2]),
+                        channel(alpha * factor[3]),
+                    )
+                })
+                .collect();
+            mesh = mesh.with_colors(colors).ok_or_else(|| invalid("colors"))?;
+        }
+        let color = Color::new(
+            channel(factor[0]),
+            channel(factor[1]),
+            channel(factor[2]),
+            channel(factor[3]),
+        );
+        Ok(Some((mesh, color)))
+    }
+
+    fn base_color(&self, primitive: &Json) -> Result<Vec<f64>, FormatError> {
+        let material = match primitive.get("material").and_then(Json::as_usize) {
+            Some(idx) => self.item("materials", idx)?,
+            None => return Ok(vec![1.; 4]),
+        };
+        match material
+            .get("pbrMetallicRoughness")
+            .and_then(|pbr| pbr.get("baseColorFactor"))
+        {
+            None => Ok(vec![1.; 4]),
+            Some(factor) => factor
+                .as_numbers()
+                .filter(|f| f.len() == 4)
+                .ok_or_else(|| FormatError::Invalid("invalid `baseColorFactor`".to_string())),
+        }
+    }
+
+    /// Bytes of the buffer, only the binary chunk of the file can be used
+    fn buffer(&self, idx: usize) -> Result<&'a [u8], FormatError> {
+        let buffer = self.item("buffers", idx)?;
+        if buffer.get("uri").is_some() {
+            return Err(FormatError::Unsupported(
+                "buffers outside of the binary chunk".to_string(),
+            ));
+        }
+        match self.bin {
+            Some(bin) if idx == 0 => Ok(bin),
+            _ => Err(FormatError::Invalid(format!("no data for buffer {}", idx))),
+        }
+    }
+
+    /// Values of the accessor, every value has at least `min_components` components
+    fn accessor(&self, idx: usize, min_components: usize) -> Result<Vec<Vec<f64>>, FormatError> {
+        let accessor = self.item("accessors", idx)?;
+        let invalid = |message: &str| FormatError::Invalid(format!("accessor {} {}", idx, message));
+        if accessor.get("sparse").is_some() {
+            return Err(FormatError::Unsupported("sparse accessors".to_string()));
+        }
+        let count = accessor
+            .get("count")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid("has no `count`"))?;
+        let component_type = accessor
+            .get("componentType")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid("has no `componentType`"))?;
+        let size = component_size(component_type)
+            .ok_or_else(|| invalid("has an unknown `componentType`"))?;
+        let components = accessor
+            .get("type")
+            .and_then(Json::as_str)
+            .and_then(components)
+            .ok_or_else(|| invalid("has an unknown `type`"))?;
+        if components < min_components {
+            return Err(invalid("has too few components"));
+        }
+        let normalized = accessor
+            .get("normalized")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+
+        // accessors without data are filled with zeros, so only `count` would limit their size
+        let view_idx = accessor
+            .get("bufferView")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| {
+                FormatError::Unsupported("accessors without buffer views".to_string())
+            })?;
+        let view = self.item("bufferViews", view_idx)?;
+        let buffer = self.buffer(
+            view.get("buffer")
+                .and_then(Json::as_usize)
+                .ok_or_else(|| invalid("has a buffer view without buffer"))?,
+        )?;
+        let offset = |json: &Json| json.get("byteOffset").and_then(Json::as_usize).unwrap_or(0);
+        let view_start = offset(view);
+        let view_length = view
+            .get("byteLength")
+            .and_then(Json::as_usize)
+            .ok_or_else(|| invalid("has a buffer view without `byteLength`"))?;
+        // offsets and lengths come from the file, so they may overflow usize
+        let view_end = view_start
+            .checked_add(view_length)
+            .filter(|end| *end <= buffer.len())
+            .ok_or_else(|| invalid("has a buffer view out of its buffer"))?;
+        let element = size * components;
+        let stride = view
+            .get("byteStride")
+            .and_then(Json::as_usize)
+            .unwrap_or(element);
+        // elements can't overlap, so `count` is limited by the size of the buffer view
+        if stride < element {
+            return Err(invalid("has a buffer view with too small `byteStride`"));
+        }
+        let base = view_start
+            .checked_add(offset(accessor))
+            .ok_or_else(|| invalid("is out of its buffer view"))?;
+
+        (0..count)
+            .map(|i| {
+                let start = i
+                    .checked_mul(stride)
+                    .and_then(|shift| shift.checked_add(base))
+                    .filter(|start| start.checked_add(element) <= Some(view_end))
+                    .ok_or_else(|| invalid("is out of its buffer view"))?;
+                Ok((0..components)
+                    .map(|c| component(&buffer[start + c * size..], component_type, normalized))
+                    .collect())
+            })
+            .collect()
+    }
+}
+
+/// Indices listed in the member of the object, empty if there is no such member
+fn indices(json: &Json, key: &str) -> Result<Vec<usize>, FormatError> {
+    match json.get(key) {
+        None => Ok(Vec::new()),
+        Some(value) => value
+            .as_array()
+            .and_then(|a| a.iter().map(Json::as_usize).collect())
+            .ok_or_else(|| FormatError::Invalid(format!("invalid `{}`", key))),
+    }
+}
+
+fn node_matrix(node: &Json, idx: usize) -> Result<Matrix, FormatError> {
+    let numbers = |key: &str, default: &[f64]| match node.get(key) {
+        None => Ok(default.to_vec()),
+        Some(value) => value
+            .as_numbers()
+            .filter(|n| n.len() == default.len())
+            .ok_or_else(|| FormatError::Invalid(format!("node {} has invalid `{}`", idx, key))),
+    };
+    if node.get("matrix").is_some() {
+        let mut m = [0.; 16];
+        m.copy_from_slice(&numbers("matrix", &Matrix::identity().0)?);
+        return Ok(Matrix(m));
+    }
+    Ok(Matrix::from_trs(
+        &numbers("translation", &[0., 0., 0.])?,
+        &numbers("rotation", &[0., 0., 0., 1.])?,
+        &numbers("scale", &[1., 1., 1.])?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Binary file with the JSON and the binary chunks padded to 4 bytes
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let padding = |len: usize| (4 - len % 4) % 4;
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len() + padding(json.len()), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len() + padding(bin.len()), 0);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&2_u32.to_le_bytes());
+        let length = HEADER_LEN + 2 * CHUNK_HEADER_LEN + json.len() + bin.len();
+        bytes.extend_from_slice(&(length as u32).to_le_bytes());
+        for (chunk, chunk_type) in [(json, CHUNK_JSON), (bin, CHUNK_BIN)].iter() {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&chunk_type.to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    /// Square in the XY plane facing +z, made of positions and indices of two triangles
+    fn square_bin() -> Vec<u8> {
+        let mut bin = Vec::new();
+        for v in [0_f32, 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.].iter() {
+            bin.extend_from_slice(&v.to_le_bytes());
+        }
+        for i in [0_u16, 1, 2, 0, 2, 3].iter() {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        bin
+    }
+
+    const SQUARE: &str = r#"{
+  "asset": {"version": "2.0"},
+  "scene": 0,
+  "scenes": [{"nodes": [0]}],
+  "nodes": [
+    {"name": "parent", "translation": [10, 0, 0], "children": [1]},
+    {"mesh": 0, "scale": [2, 2, 2]}
+  ],
+  "meshes": [{"name": "square", "primitives": [
+    {"attributes": {"POSITION": 0}, "indices": 1, "material": 0}
+  ]}],
+  "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0.5, 0, 1]}}],
+  "accessors": [
+    {"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3"},
+    {"bufferView": 1, "componentType": 5123, "count": 6, "type": "SCALAR"}
+  ],
+  "bufferViews": [
+    {"buffer": 0, "byteOffset": 0, "byteLength": 48},
+    {"buffer": 0, "byteOffset": 48, "byteLength": 12}
+  ],
+  "buffers": [{"byteLength": 60}]
+}"#;
+
+    #[test]
+    fn hierarchy_and_materials() {
+        let model = GltfModel::parse_glb(&glb(SQUARE, &square_bin())).unwrap();
+        assert_eq!(model.meshes.len(), 1);
+        let square = &model.meshes[0];
+        assert_eq!(square.name, "square");
+        assert_eq!(square.color, Color::rgb(255, 128, 0));
+        assert_eq!(square.mesh.positions[2], Point3::new(12., 2., 0.));
+        assert_eq!(square.mesh.indices, vec![[0, 2, 1], [0, 3, 2]]);
+
+        let triangles = model.approximate();
+        assert_eq!(triangles.len(), 2);
+        // counter clockwise faces of glTF look at +z
+        assert!(triangles.iter().all(|t| t.normal().z > 0.));
+    }
+
+    #[test]
+    fn matrix_transforms() {
+        let quarter = std::f64::consts::FRAC_PI_4;
+        // rotation by 90 degrees around z
+        let m = Matrix::from_trs(
+            &[1., 0., 0.],
+            &[0., 0., quarter.sin(), quarter.cos()],
+            &[1., 1., 1.],
+        );
+        let p = m.point(&Point3::new(1., 0., 0.));
+        assert!(p.sub(&Point3::new(1., 1., 0.)).length() < 1e-9);
+
+        // normals stay perpendicular to stretched surfaces
+        let stretch = Matrix::from_trs(&[0., 0., 0.], &[0., 0., 0., 1.], &[2., 1., 1.]);
+        let n = stretch.normal(&Vector3::new(1., 1., 0.).normalize());
+        let tangent = stretch.point(&Point3::new(1., -1., 0.));
+        assert!(n.dot_product(&tangent).abs() < 1e-9);
+
+        let mirror = Matrix::from_trs(&[0., 0., 0.], &[0., 0., 0., 1.], &[-1., 1., 1.]);
+        assert_eq!(mirror.determinant(), -1.);
+        assert_eq!(m.mul(&Matrix::identity()), m);
+    }
+
+    #[test]
+    fn errors() {
+        let bin = square_bin();
+        let error = |json: &str| GltfModel::parse_glb(&glb(json, &bin)).unwrap_err();
+        assert_eq!(
+            error(&SQUARE.replace(
+                r#""scene": 0,"#,
+                r#""scene": 0, "extensionsRequired": ["KHR_draco_mesh_compression"],"#
+            )),
+            FormatError::Unsupported("extension `KHR_draco_mesh_compression`".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""version": "2.0""#, r#""version": "1.0""#)),
+            FormatError::Unsupported("glTF version 1.0".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""count": 6"#, r#""count": 7"#)),
+            FormatError::Invalid("accessor 1 is out of its buffer view".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""count": 6"#, r#""count": 4294967295"#)),
+            FormatError::Invalid("accessor 1 is out of its buffer view".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(
+                r#""bufferView": 1, "componentType": 5123"#,
+                r#""bufferView": 1, "byteOffset": 1e300, "componentType": 5123"#
+            )),
+            FormatError::Invalid("accessor 1 is out of its buffer view".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(
+                r#""bufferView": 1, "componentType": 5123, "count": 6"#,
+                r#""componentType": 5123, "count": 100000000000"#
+            )),
+            FormatError::Unsupported("accessors without buffer views".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""children": [1]"#, r#""children": [1, 0]"#)),
+            FormatError::Invalid("node 0 is reached twice".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""children": [1]"#, r#""children": [1, 1]"#)),
+            FormatError::Invalid("node 1 is reached twice".to_string())
+        );
+        assert_eq!(
+            error(&SQUARE.replace(r#""mesh": 0"#, r#""mesh": 1"#)),
+            FormatError::Invalid("no meshes 1".to_string())
+        );
+        assert_eq!(
+            error("{\n\"asset\": ]"),
+            FormatError::syntax(2, "unexpected `]`")
+        );
+
+        let bytes = glb(SQUARE, &bin);
+        assert_eq!(
+            GltfModel::parse_glb(&bytes[..bytes.len() - 1]),
+            Err(FormatError::binary(
+                bytes.len() - 1,
+                format!("file needs {} bytes", bytes.len())
+            ))
+        );
+        // the length of the JSON chunk overflows usize in wasm
+        let mut huge = bytes.clone();
+        huge[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(GltfModel::parse_glb(&huge).is_err());
+        assert_eq!(
+            GltfModel::parse_glb(b"glTF\x01\0\0\0\x0c\0\0\0"),
+            Err(FormatError::Unsupported("glTF version 1".to_string()))
+        );
+        assert_eq!(
+            GltfModel::parse_glb(b"solid not a glb"),
+            Err(FormatError::binary(0, "not a glTF binary file"))
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Images of rasterized frames: PNG with uncompressed deflate blocks and binary PPM
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::geometry::color::Color;
+use crate::geometry::raster::FrameBuffer;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// Deflate blocks without compression can't be longer
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+/// RGBA PNG image of the frame, 8 bits per channel without compression
+pub fn png(frame: &FrameBuffer) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&(frame.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(frame.height() as u32).to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // every row starts with the type of its filter, 0 leaves it as it is
+    let row_len = frame.width() * 4;
+    let mut raw = Vec::with_capacity((row_len + 1) * frame.height());
+    for row in frame.pixels().chunks(row_len.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut bytes = PNG_SIGNATURE.to_vec();
+    chunk(&mut bytes, b"IHDR", &header);
+    chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut bytes, b"IEND", &[]);
+    bytes
+}
+
+/// Binary RGB PPM (P6) image of the frame,
+/// the format has no alpha so pixels are put over the background
+pub fn ppm(frame: &FrameBuffer, background: Color) -> Vec<u8> {
+    let mut bytes = format!("P6\n{} {}\n255\n", frame.width(), frame.height()).into_bytes();
+    for p in frame.pixels().chunks(4) {
+        let alpha = p[3] as f64 / 255.;
+        let over =
+            |top: u8, bottom: u8| (top as f64 * alpha + bottom as f64 * (1. - alpha)).round() as u8;
+        bytes.push(over(p[0], background.r));
+        bytes.push(over(p[1], background.g));
+        bytes.push(over(p[2], background.b));
+    }
+    bytes
+}
+
+pub fn save_png(frame: &FrameBuffer, path: &Path) -> io::Result<()> {
+    fs::write(path, png(frame))
+}
+
+pub fn save_ppm(frame: &FrameBuffer, background: Color, path: &Path) -> io::Result<()> {
+    fs::write(path, ppm(frame, background))
+}
+
+/// Length, type, data and the checksum of the type and the data
+fn chunk(bytes: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(chunk_type);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// zlib stream of deflate blocks which store the data as it is
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate with a 32K window and the fastest compression level
+    let mut bytes = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = if data.is_empty() {
+        vec![&[]]
+    } else {
+        data.chunks(MAX_STORED_BLOCK).collect()
+    };
+    for (idx, block) in blocks.iter().enumerate() {
+        let last = idx + 1 == blocks.len();
+        // the first bit marks the last block, the next two bits 00 mean no compression
+        bytes.push(last as u8);
+        let len = block.len() as u16;
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&(!len).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+    bytes
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut table = [0_u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !bytes.iter().fold(!0_u32, |crc, &b| {
+        table[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1_u32, 0_u32);
+    for &byte in bytes.iter() {
+        a = (a + byte as u32) % MOD;
+        b = (b + a) % MOD;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::g3d::{Point3, Triagnle3};
+
+    fn frame() -> FrameBuffer {
+        let mut frame = FrameBuffer::new(3, 2);
+        frame.clear(Color::new(0, 0, 255, 0));
+        frame.draw_triangle(
+            &Triagnle3::new(
+                Point3::new(0., 0., 0.),
+                Point3::new(2.9, 0., 0.),
+                Point3::new(0., 2.9, 0.),
+            ),
+            Color::new(255, 0, 0, 255),
+        );
+        frame
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn stored_blocks() {
+        let data = vec![7; MAX_STORED_BLOCK + 10];
+        let zlib = zlib_stored(&data);
+        assert_eq!(zlib.len(), 2 + 2 * 5 + data.len() + 4);
+        // first block is full and not the last one
+        assert_eq!(zlib[2..7], [0, 0xFF, 0xFF, 0, 0]);
+        let second = 7 + MAX_STORED_BLOCK;
+        assert_eq!(zlib[second..second + 5], [1, 10, 0, 0xF5, 0xFF]);
+    }
+
+    #[test]
+    fn png_of_frame() {
+        let frame = frame();
+        let png = png(&frame);
+        assert_eq!(png[..8], *PNG_SIGNATURE);
+        // IHDR
+        assert_eq!(png[8..16], [0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+        assert_eq!(png[16..29], [0, 0, 0, 3, 0, 0, 0, 2, 8, 6, 0, 0, 0]);
+        assert_eq!(
+            u32::from_be_bytes([png[29], png[30], png[31], png[32]]),
+            crc32(&png[12..29])
+        );
+        // IDAT has the rows of pixels after the zlib header and the header of the block
+        let row_len = 1 + 3 * 4;
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(idat_len, 2 + 5 + 2 * row_len + 4);
+        let rows = &png[37 + 4 + 7..37 + 4 + 7 + 2 * row_len];
+        assert_eq!(
+            rows[..row_len],
+            [0, 255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 255, 0]
+        );
+        assert_eq!(rows[row_len..row_len + 5], [0, 255, 0, 0, 255]);
+        // IEND
+        assert_eq!(
+            png[png.len() - 12..],
+            [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+        );
+    }
+
+    #[test]
+    fn ppm_of_frame() {
+        let ppm = ppm(&frame(), Color::rgb(255, 255, 255));
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(ppm[..header.len()], *header);
+        let pixels = &ppm[header.len()..];
+        assert_eq!(pixels.len(), 3 * 2 * 3);
+        assert_eq!(pixels[..3], [255, 0, 0]);
+        // transparent pixel shows the background
+        assert_eq!(pixels[pixels.len() - 3..], [255, 255, 255]);
+    }
+}
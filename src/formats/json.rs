@@ -0,0 +1,416 @@
+//! JSON values, enough to read glTF files and to save scenes
+
+use std::fmt;
+
+use super::FormatError;
+
+/// Arrays and objects can't be nested deeper, so the recursive parser doesn't overflow the stack
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order of the text, so written objects keep the order they are built in
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, FormatError> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unexpected text after the value"));
+        }
+        Ok(value)
+    }
+
+    /// Member of the object, None for other values
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Number which is a non-negative integer
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|n| *n >= 0. && n.fract() == 0.)
+            .map(|n| n as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Array of numbers
+    pub fn as_numbers(&self) -> Option<Vec<f64>> {
+        self.as_array()?.iter().map(Json::as_f64).collect()
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl std::iter::FromIterator<Json> for Json {
+    fn from_iter<I: IntoIterator<Item = Json>>(iter: I) -> Self {
+        Json::Array(iter.into_iter().collect())
+    }
+}
+
+/// Compact text without spaces, numbers which are not finite are written as null
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) if n.is_finite() => write!(f, "{}", n),
+            Json::Number(_) => write!(f, "null"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    /// line of the current position
+    line: usize,
+    /// number of arrays and objects around the current position
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> FormatError {
+        FormatError::syntax(self.line, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if let Some(c) = c {
+            self.pos += 1;
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(&format!("expected `{}`, found `{}`", expected, c))),
+            None => Err(self.error("unexpected end of the text")),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, FormatError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c @ '{') | Some(c @ '[') => {
+                if self.depth == MAX_DEPTH {
+                    return Err(self.error("arrays and objects are nested too deeply"));
+                }
+                self.depth += 1;
+                let value = if c == '{' {
+                    self.object()
+                } else {
+                    self.array()
+                };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-') | Some('0'..='9') => self.number(),
+            Some(c) if c.is_alphabetic() => {
+                let start = self.pos;
+                while self.peek().is_some_and(char::is_alphabetic) {
+                    self.next();
+                }
+                let word: String = self.chars[start..self.pos].iter().collect();
+                match word.as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => Err(self.error(&format!("unknown value `{}`", word))),
+                }
+            }
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c))),
+            None => Err(self.error("unexpected end of the text")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, FormatError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected the name of a member"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, FormatError> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, FormatError> {
+        let start = self.pos;
+        while let Some('-') | Some('+') | Some('.') | Some('e') | Some('E') | Some('0'..='9') =
+            self.peek()
+        {
+            self.next();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("invalid number `{}`", text)))
+    }
+
+    fn hex(&mut self) -> Result<u32, FormatError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("invalid `\\u` escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.hex()?;
+                            // characters out of the basic plane are written as surrogate pairs
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.next() != Some('\\') || self.next() != Some('u') {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                let low = self.hex()?;
+                                if !(0xDC00..=0xDFFF).contains(&low) {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            std::char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid `\\u` escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        let text = r#"{
+  "name": "a \"quoted\" é😀",
+  "values": [1, -2.5, 3e2, true, null],
+  "empty": {}
+}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(
+            json.get("name").and_then(Json::as_str),
+            Some("a \"quoted\" é😀")
+        );
+        assert_eq!(
+            json.get("values").unwrap().as_array().unwrap()[..3],
+            [Json::Number(1.), Json::Number(-2.5), Json::Number(300.)]
+        );
+        assert_eq!(json.get("empty"), Some(&Json::Object(vec![])));
+        assert_eq!(json.get("missing"), None);
+
+        let written = json.to_string();
+        assert_eq!(
+            written,
+            r#"{"name":"a \"quoted\" é😀","values":[1,-2.5,300,true,null],"empty":{}}"#
+        );
+        assert_eq!(Json::parse(&written), Ok(json));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Json::parse("{\n\"a\": [1,\n 2 3]}"),
+            Err(FormatError::syntax(3, "expected `,` or `]`"))
+        );
+        assert_eq!(
+            Json::parse("[nul]"),
+            Err(FormatError::syntax(1, "unknown value `nul`"))
+        );
+        assert_eq!(
+            Json::parse("\"open"),
+            Err(FormatError::syntax(1, "unterminated string"))
+        );
+        assert_eq!(
+            Json::parse("1 2"),
+            Err(FormatError::syntax(1, "unexpected text after the value"))
+        );
+        assert_eq!(
+            Json::parse(r#""\ud83d\u0041""#),
+            Err(FormatError::syntax(1, "unpaired surrogate"))
+        );
+
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            Json::parse(&nested(100_000)),
+            Err(FormatError::syntax(
+                1,
+                "arrays and objects are nested too deeply"
+            ))
+        );
+    }
+}
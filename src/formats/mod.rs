@@ -0,0 +1,65 @@
+//! Reading models from files of other programs, writing images for them
+//! and saving scenes
+
+use std::fmt;
+
+pub mod gltf;
+pub mod image;
+pub mod json;
+pub mod obj;
+pub mod ply;
+pub mod scene;
+pub mod stl;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    /// Malformed line of a text file, lines are counted from 1
+    Syntax { line: usize, message: String },
+    /// Malformed binary file, `offset` is the position of the wrong data in bytes
+    Binary { offset: usize, message: String },
+    /// File which breaks rules of its format in a way which has no line or offset,
+    /// like references to missing parts of the file
+    Invalid(String),
+    /// Valid file which uses features which are not supported
+    Unsupported(String),
+    /// File can't be read
+    Io(String),
+}
+
+impl FormatError {
+    fn syntax<S: Into<String>>(line: usize, message: S) -> Self {
+        FormatError::Syntax {
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn binary<S: Into<String>>(offset: usize, message: S) -> Self {
+        FormatError::Binary {
+            offset,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            FormatError::Binary { offset, message } => {
+                write!(f, "byte {}: {}", offset, message)
+            }
+            FormatError::Invalid(message) => write!(f, "{}", message),
+            FormatError::Unsupported(feature) => write!(f, "{} is not supported", feature),
+            FormatError::Io(message) => write!(f, "can't read the file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<std::io::Error> for FormatError {
+    fn from(error: std::io::Error) -> Self {
+        FormatError::Io(error.to_string())
+    }
+}
@@ -0,0 +1,371 @@
+//! Wavefront OBJ models with MTL materials, only colors of materials are used
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::FormatError;
+use crate::geometry::color::Color;
+use crate::geometry::g2d::Point2;
+use crate::geometry::g3d::{Point3, Triagnle3, Vector3};
+use crate::geometry::Shape;
+
+const DEFAULT_GROUP: &str = "default";
+
+/// Triangles of the faces given after a `g` or `o` statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub triangles: Vec<Triagnle3>,
+}
+
+/// Polygons are split into triangles by fans from their first vertex.
+/// Vertices of faces go counter clockwise when looked at from the outside,
+/// so the order of vertices of triangles is reversed to make `Triagnle3::normal` point outside.
+/// Texture coordinates are flipped vertically, as OBJ counts them from the bottom of the image
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+}
+
+impl ObjModel {
+    /// `mtl` is the content of the material libraries used by the model
+    pub fn parse(obj: &str, mtl: Option<&str>) -> Result<Self, FormatError> {
+        let materials = match mtl {
+            Some(mtl) => parse_mtl(mtl)?,
+            None => HashMap::new(),
+        };
+
+        let mut points: Vec<Point3> = Vec::new();
+        let mut uvs: Vec<Point2> = Vec::new();
+        let mut normals: Vec<Vector3> = Vec::new();
+        let mut groups: Vec<ObjGroup> = Vec::new();
+        let mut group = 0;
+        let mut color: Option<Color> = None;
+
+        for (line, tokens) in statements(obj) {
+            match tokens[0] {
+                "v" => {
+                    let v = numbers(line, &tokens[1..], 3)?;
+                    points.push(Point3::new(v[0], v[1], v[2]));
+                }
+                "vt" => {
+                    let v = numbers(line, &tokens[1..], 1)?;
+                    let v_coord = v.get(1).cloned().unwrap_or(0.);
+                    uvs.push(Point2::new(v[0], 1. - v_coord));
+                }
+                "vn" => {
+                    let v = numbers(line, &tokens[1..], 3)?;
+                    normals.push(Vector3::new(v[0], v[1], v[2]));
+                }
+                "g" | "o" => {
+                    let name = if tokens.len() > 1 {
+                        tokens[1..].join(" ")
+                    } else {
+                        DEFAULT_GROUP.to_string()
+                    };
+                    group = group_index(&mut groups, &name);
+                }
+                "usemtl" => {
+                    color = tokens.get(1).and_then(|name| materials.get(*name)).cloned();
+                }
+                "f" => {
+                    if tokens.len() < 4 {
+                        return Err(FormatError::syntax(line, "face needs at least 3 vertices"));
+                    }
+                    let vertices = tokens[1..]
+                        .iter()
+                        .map(|t| vertex(line, t, &points, &uvs, &normals))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if groups.is_empty() {
+                        group = group_index(&mut groups, DEFAULT_GROUP);
+                    }
+                    for i in 1..vertices.len() - 1 {
+                        let triangle = triangle([&vertices[0], &vertices[i], &vertices[i + 1]]);
+                        let triangle = match color {
+                            Some(color) => triangle.with_colors([color, color, color]),
+                            None => triangle,
+                        };
+                        groups[group].triangles.push(triangle);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        groups.retain(|g| !g.triangles.is_empty());
+        Ok(ObjModel { groups })
+    }
+
+    /// Reads the model and the material libraries it refers to,
+    /// paths of the libraries are relative to the model
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        let obj = fs::read_to_string(path)?;
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut mtl = String::new();
+        for (_, tokens) in statements(&obj).filter(|(_, t)| t[0] == "mtllib") {
+            for name in tokens[1..].iter() {
+                mtl.push_str(&fs::read_to_string(directory.join(name))?);
+                mtl.push('\n');
+            }
+        }
+        ObjModel::parse(&obj, Some(&mtl))
+    }
+
+    pub fn group(&self, name: &str) -> Option<&ObjGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+}
+
+impl Shape for ObjModel {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.groups
+            .iter()
+            .flat_map(|g| g.triangles.iter().cloned())
+            .collect()
+    }
+}
+
+/// Diffuse colors of materials by their names, the dissolve factor becomes the alpha
+pub fn parse_mtl(mtl: &str) -> Result<HashMap<String, Color>, FormatError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<String> = None;
+    for (line, tokens) in statements(mtl) {
+        match tokens[0] {
+            "newmtl" => {
+                let name = tokens[1..].join(" ");
+                materials.insert(name.clone(), Color::rgb(255, 255, 255));
+                current = Some(name);
+            }
+            "Kd" | "d" => {
+                let material = current
+                    .as_ref()
+                    .and_then(|name| materials.get_mut(name))
+                    .ok_or_else(|| FormatError::syntax(line, "no material is defined"))?;
+                if tokens[0] == "Kd" {
+                    let v = numbers(line, &tokens[1..], 3)?;
+                    *material = Color::new(channel(v[0]), channel(v[1]), channel(v[2]), material.a);
+                } else {
+                    material.a = channel(numbers(line, &tokens[1..], 1)?[0]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+/// Non empty lines with their numbers, comments are removed
+fn statements(text: &str) -> impl Iterator<Item = (usize, Vec<&str>)> {
+    text.lines().enumerate().filter_map(|(idx, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            None
+        } else {
+            Some((idx + 1, tokens))
+        }
+    })
+}
+
+/// Parses all the tokens, there have to be at least `count` of them
+fn numbers(line: usize, tokens: &[&str], count: usize) -> Result<Vec<f64>, FormatError> {
+    if tokens.len() < count {
+        return Err(FormatError::syntax(
+            line,
+            format!("expected {} numbers, found {}", count, tokens.len()),
+        ));
+    }
+    tokens
+        .iter()
+        .map(|t| {
+            t.parse::<f64>()
+                .map_err(|_| FormatError::syntax(line, format!("invalid number `{}`", t)))
+        })
+        .collect()
+}
+
+fn channel(value: f64) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+fn group_index(groups: &mut Vec<ObjGroup>, name: &str) -> usize {
+    match groups.iter().position(|g| g.name == name) {
+        Some(idx) => idx,
+        None => {
+            groups.push(ObjGroup {
+                name: name.to_string(),
+                triangles: Vec::new(),
+            });
+            groups.len() - 1
+        }
+    }
+}
+
+struct Vertex {
+    point: Point3,
+    uv: Option<Point2>,
+    normal: Option<Vector3>,
+}
+
+/// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`
+fn vertex(
+    line: usize,
+    token: &str,
+    points: &[Point3],
+    uvs: &[Point2],
+    normals: &[Vector3],
+) -> Result<Vertex, FormatError> {
+    let mut parts = token.split('/');
+    let point = points[index(line, parts.next().unwrap_or(""), points.len())?];
+    let uv = match parts.next() {
+        Some(idx) if !idx.is_empty() => Some(uvs[index(line, idx, uvs.len())?]),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(idx) if !idx.is_empty() => Some(normals[index(line, idx, normals.len())?]),
+        _ => None,
+    };
+    Ok(Vertex { point, uv, normal })
+}
+
+/// Indexes start from 1, negative indexes count from the last element
+fn index(line: usize, token: &str, len: usize) -> Result<usize, FormatError> {
+    let invalid = || FormatError::syntax(line, format!("invalid index `{}`", token));
+    let idx: i64 = token.parse().map_err(|_| invalid())?;
+    let idx = if idx > 0 { idx - 1 } else { len as i64 + idx };
+    if idx >= 0 && (idx as usize) < len {
+        Ok(idx as usize)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Triangle with vertices in the reversed order
+fn triangle(vertices: [&Vertex; 3]) -> Triagnle3 {
+    let vertices = [vertices[0], vertices[2], vertices[1]];
+    let triangle = Triagnle3::new(vertices[0].point, vertices[1].point, vertices[2].point);
+    let triangle = match (vertices[0].uv, vertices[1].uv, vertices[2].uv) {
+        (Some(a), Some(b), Some(c)) => triangle.with_uvs([a, b, c]),
+        _ => triangle,
+    };
+    match (vertices[0].normal, vertices[1].normal, vertices[2].normal) {
+        (Some(a), Some(b), Some(c)) => triangle.with_normals([a, b, c]),
+        _ => triangle,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CUBE_SIDE: &str = "
+        # two sides of a cube
+        mtllib cube.mtl
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        v 0 0 1
+        vt 0 0
+        vt 1 0
+        vt 1 1
+        vn 0 0 1
+
+        g front
+        usemtl red
+        f 1/1/1 2/2/1 3/3/1 4/1/1
+        g bottom
+        usemtl missing
+        f -5 -4 -1
+    ";
+
+    const MTL: &str = "
+        newmtl red
+        Kd 1 0 0.5
+        d 0.5
+    ";
+
+    #[test]
+    fn groups_and_materials() {
+        let model = ObjModel::parse(CUBE_SIDE, Some(MTL)).unwrap();
+        assert_eq!(model.groups.len(), 2);
+
+        let front = model.group("front").unwrap();
+        assert_eq!(front.triangles.len(), 2);
+        let red = Color::new(255, 0, 128, 128);
+        let first = &front.triangles[0];
+        assert_eq!(first.p2, Point3::new(1., 1., 0.));
+        assert_eq!(first.colors, Some([red, red, red]));
+        assert_eq!(first.normals.unwrap()[0], Vector3::new(0., 0., 1.));
+        assert!(first.normal().z > 0.);
+        assert_eq!(
+            first.uvs,
+            Some([
+                Point2::new(0., 1.),
+                Point2::new(1., 0.),
+                Point2::new(1., 1.)
+            ])
+        );
+        // fan from the first vertex
+        assert_eq!(front.triangles[1].p1, Point3::new(0., 0., 0.));
+        assert_eq!(front.triangles[1].p2, Point3::new(0., 1., 0.));
+
+        let bottom = model.group("bottom").unwrap();
+        assert_eq!(
+            bottom.triangles,
+            vec![Triagnle3::new(
+                Point3::new(0., 0., 0.),
+                Point3::new(0., 0., 1.),
+                Point3::new(1., 0., 0.),
+            )]
+        );
+        assert_eq!(model.approximate().len(), 3);
+    }
+
+    #[test]
+    fn faces_without_groups() {
+        let model = ObjModel::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", None).unwrap();
+        assert_eq!(model.groups[0].name, DEFAULT_GROUP);
+        assert_eq!(model.groups[0].triangles[0].colors, None);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |obj: &str| ObjModel::parse(obj, None).unwrap_err();
+        assert_eq!(
+            error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4"),
+            FormatError::syntax(4, "invalid index `4`")
+        );
+        assert_eq!(
+            error("v 0 0\n"),
+            FormatError::syntax(1, "expected 3 numbers, found 2")
+        );
+        assert_eq!(
+            error("v 0 x 0\n"),
+            FormatError::syntax(1, "invalid number `x`")
+        );
+        assert_eq!(
+            error("v 0 0 0\nf 1 1\n"),
+            FormatError::syntax(2, "face needs at least 3 vertices")
+        );
+        assert_eq!(
+            parse_mtl("Kd 1 1 1").unwrap_err(),
+            FormatError::syntax(1, "no material is defined")
+        );
+    }
+
+    #[test]
+    fn load_with_materials() {
+        let directory = std::env::temp_dir().join("playing-with-canvas-obj");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("cube.obj"), CUBE_SIDE).unwrap();
+        fs::write(directory.join("cube.mtl"), MTL).unwrap();
+        let model = ObjModel::load(&directory.join("cube.obj")).unwrap();
+        assert_eq!(model, ObjModel::parse(CUBE_SIDE, Some(MTL)).unwrap());
+        assert!(matches!(
+            ObjModel::load(&directory.join("missing.obj")),
+            Err(FormatError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,558 @@
+//! PLY (Polygon File Format) models in the ASCII and the binary little endian forms
+
+use std::fs;
+use std::path::Path;
+
+use super::FormatError;
+use crate::geometry::color::Color;
+use crate::geometry::g3d::{Point3, Triagnle3, Vector3};
+use crate::geometry::Shape;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyVertex {
+    pub point: Point3,
+    pub color: Option<Color>,
+    pub normal: Option<Vector3>,
+}
+
+/// Vertices of the model and triangles of its faces, point clouds without faces are not supported.
+/// Faces are split into triangles by fans from their first vertex.
+/// Vertices of faces go counter clockwise when looked at from the outside,
+/// so the order of vertices of triangles is reversed to make `Triagnle3::normal` point outside
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyModel {
+    pub vertices: Vec<PlyVertex>,
+    pub triangles: Vec<Triagnle3>,
+}
+
+impl PlyModel {
+    pub fn parse(bytes: &[u8]) -> Result<Self, FormatError> {
+        let (header, body) = Header::parse(bytes)?;
+        // lines of the text body are counted after the line with `end_header`
+        let header_lines = header.lines;
+        let mut reader = match header.format {
+            Format::Ascii => {
+                let text = std::str::from_utf8(&bytes[body..])
+                    .map_err(|e| FormatError::binary(body + e.valid_up_to(), "invalid text"))?;
+                Reader::Ascii {
+                    words: text
+                        .lines()
+                        .enumerate()
+                        .flat_map(|(idx, line)| {
+                            line.split_whitespace()
+                                .map(move |w| (idx + header_lines + 1, w))
+                        })
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                    line: header_lines,
+                }
+            }
+            Format::BinaryLittleEndian => Reader::Binary {
+                bytes,
+                offset: body,
+            },
+        };
+
+        let vertex_count = header
+            .elements
+            .iter()
+            .find(|e| e.name == "vertex")
+            .map_or(0, |e| e.count);
+        let mut vertices = Vec::new();
+        let mut faces: Vec<Vec<usize>> = Vec::new();
+        for element in header.elements.iter() {
+            for _ in 0..element.count {
+                let values = element
+                    .properties
+                    .iter()
+                    .map(|p| reader.property(p))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match element.name.as_str() {
+                    "vertex" => vertices.push(element.vertex(&values)?),
+                    "face" => faces.push(element.face(&values, &reader, vertex_count)?),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut triangles = Vec::new();
+        for face in faces.iter() {
+            for i in 1..face.len().saturating_sub(1) {
+                triangles.push(triangle([
+                    &vertices[face[0]],
+                    &vertices[face[i + 1]],
+                    &vertices[face[i]],
+                ]));
+            }
+        }
+        if triangles.is_empty() {
+            return Err(FormatError::Unsupported("model without faces".to_string()));
+        }
+        Ok(PlyModel {
+            vertices,
+            triangles,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FormatError> {
+        PlyModel::parse(&fs::read(path)?)
+    }
+}
+
+impl Shape for PlyModel {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.triangles.clone()
+    }
+}
+
+fn triangle(vertices: [&PlyVertex; 3]) -> Triagnle3 {
+    let triangle = Triagnle3::new(vertices[0].point, vertices[1].point, vertices[2].point);
+    let triangle = match (vertices[0].color, vertices[1].color, vertices[2].color) {
+        (Some(a), Some(b), Some(c)) => triangle.with_colors([a, b, c]),
+        _ => triangle,
+    };
+    match (vertices[0].normal, vertices[1].normal, vertices[2].normal) {
+        (Some(a), Some(b), Some(c)) => triangle.with_normals([a, b, c]),
+        _ => triangle,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(line: usize, name: &str) -> Result<Self, FormatError> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => {
+                return Err(FormatError::syntax(
+                    line,
+                    format!("unknown type `{}`", name),
+                ))
+            }
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(self, Scalar::F32 | Scalar::F64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Property {
+    name: String,
+    /// type of the length of the list, None for scalar properties
+    list: Option<Scalar>,
+    scalar: Scalar,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn scalar(&self, values: &[Vec<f64>], name: &str) -> Option<(f64, Scalar)> {
+        self.properties
+            .iter()
+            .position(|p| p.name == name && p.list.is_none())
+            .map(|idx| (values[idx][0], self.properties[idx].scalar))
+    }
+
+    fn vertex(&self, values: &[Vec<f64>]) -> Result<PlyVertex, FormatError> {
+        let coordinate = |name: &str| {
+            self.scalar(values, name)
+                .map(|(v, _)| v)
+                .ok_or_else(|| FormatError::Unsupported(format!("vertex without `{}`", name)))
+        };
+        let point = Point3::new(coordinate("x")?, coordinate("y")?, coordinate("z")?);
+
+        // integer channels go from 0 to 255, float ones from 0 to 1
+        let channel = |name: &str| {
+            self.scalar(values, name).map(|(v, scalar)| {
+                let v = if scalar.is_float() { v * 255. } else { v };
+                v.round().clamp(0., 255.) as u8
+            })
+        };
+        let color = match (channel("red"), channel("green"), channel("blue")) {
+            (Some(r), Some(g), Some(b)) => {
+                Some(Color::new(r, g, b, channel("alpha").unwrap_or(255)))
+            }
+            _ => None,
+        };
+
+        let component = |name: &str| self.scalar(values, name).map(|(v, _)| v);
+        let normal = match (component("nx"), component("ny"), component("nz")) {
+            (Some(x), Some(y), Some(z)) => Some(Vector3::new(x, y, z)),
+            _ => None,
+        };
+        Ok(PlyVertex {
+            point,
+            color,
+            normal,
+        })
+    }
+
+    /// Indices of vertices of the face, they have to be less than `vertices`
+    fn face(
+        &self,
+        values: &[Vec<f64>],
+        reader: &Reader,
+        vertices: usize,
+    ) -> Result<Vec<usize>, FormatError> {
+        let idx = self
+            .properties
+            .iter()
+            .position(|p| {
+                p.list.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+            })
+            .ok_or_else(|| FormatError::Unsupported("face without `vertex_indices`".to_string()))?;
+        values[idx]
+            .iter()
+            .map(|&v| {
+                if v >= 0. && v.fract() == 0. && (v as usize) < vertices {
+                    Ok(v as usize)
+                } else {
+                    Err(reader.error(format!("invalid vertex index {}", v)))
+                }
+            })
+            .collect()
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// number of lines of the header including `end_header`
+    lines: usize,
+}
+
+impl Header {
+    /// Returns the header and the offset of the data after it
+    fn parse(bytes: &[u8]) -> Result<(Header, usize), FormatError> {
+        const END: &[u8] = b"end_header";
+        let end = bytes
+            .windows(END.len())
+            .position(|w| w == END)
+            .ok_or_else(|| FormatError::syntax(1, "no `end_header`"))?;
+        let body = bytes[end..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|p| end + p + 1)
+            .unwrap_or(bytes.len());
+        let text = String::from_utf8_lossy(&bytes[..end]);
+
+        let mut format = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let mut lines = 1;
+        for (idx, line) in text.lines().enumerate() {
+            let line_number = idx + 1;
+            lines = line_number + 1;
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if idx == 0 {
+                if tokens != ["ply"] {
+                    return Err(FormatError::syntax(1, "file has to start with `ply`"));
+                }
+                continue;
+            }
+            let error = |message: &str| FormatError::syntax(line_number, message);
+            match tokens.first().cloned() {
+                Some("format") => {
+                    format = Some(match tokens.get(1).cloned() {
+                        Some("ascii") => Format::Ascii,
+                        Some("binary_little_endian") => Format::BinaryLittleEndian,
+                        Some(other) => {
+                            return Err(FormatError::Unsupported(format!("format `{}`", other)))
+                        }
+                        None => return Err(error("no format")),
+                    });
+                }
+                Some("element") => {
+                    if tokens.len() != 3 {
+                        return Err(error("expected `element <name> <count>`"));
+                    }
+                    let count = tokens[2].parse().map_err(|_| error("invalid count"))?;
+                    elements.push(Element {
+                        name: tokens[1].to_string(),
+                        count,
+                        properties: Vec::new(),
+                    });
+                }
+                Some("property") => {
+                    let element = elements
+                        .last_mut()
+                        .ok_or_else(|| error("property before any element"))?;
+                    let property = match tokens.len() {
+                        3 => Property {
+                            name: tokens[2].to_string(),
+                            list: None,
+                            scalar: Scalar::parse(line_number, tokens[1])?,
+                        },
+                        5 if tokens[1] == "list" => Property {
+                            name: tokens[4].to_string(),
+                            list: Some(Scalar::parse(line_number, tokens[2])?),
+                            scalar: Scalar::parse(line_number, tokens[3])?,
+                        },
+                        _ => return Err(error("expected `property [list <type>] <type> <name>`")),
+                    };
+                    element.properties.push(property);
+                }
+                Some("comment") | Some("obj_info") | None => {}
+                Some(other) => {
+                    return Err(error(&format!("unknown keyword `{}`", other)));
+                }
+            }
+        }
+
+        let format = format.ok_or_else(|| FormatError::syntax(lines, "no format"))?;
+        Ok((
+            Header {
+                format,
+                elements,
+                lines,
+            },
+            body,
+        ))
+    }
+}
+
+/// Values of the body of the file
+enum Reader<'a> {
+    Ascii {
+        words: std::vec::IntoIter<(usize, &'a str)>,
+        /// line of the last word
+        line: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        offset: usize,
+    },
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: String) -> FormatError {
+        match self {
+            Reader::Ascii { line, .. } => FormatError::syntax(*line, message),
+            Reader::Binary { offset, .. } => FormatError::binary(*offset, message),
+        }
+    }
+
+    /// Value of a scalar property or all values of a list
+    fn property(&mut self, property: &Property) -> Result<Vec<f64>, FormatError> {
+        match property.list {
+            None => Ok(vec![self.value(property.scalar)?]),
+            Some(len) => {
+                let len = self.value(len)?;
+                if len < 0. || len.fract() != 0. {
+                    return Err(self.error(format!("invalid length of a list {}", len)));
+                }
+                (0..len as usize)
+                    .map(|_| self.value(property.scalar))
+                    .collect()
+            }
+        }
+    }
+
+    fn value(&mut self, scalar: Scalar) -> Result<f64, FormatError> {
+        match self {
+            Reader::Ascii { words, line } => {
+                let (word_line, word) = words
+                    .next()
+                    .ok_or_else(|| FormatError::syntax(*line, "unexpected end of the file"))?;
+                *line = word_line;
+                word.parse::<f64>().map_err(|_| {
+                    FormatError::syntax(word_line, format!("invalid number `{}`", word))
+                })
+            }
+            Reader::Binary { bytes, offset } => {
+                let size = scalar.size();
+                if *offset + size > bytes.len() {
+                    return Err(FormatError::binary(*offset, "unexpected end of the file"));
+                }
+                let b = &bytes[*offset..*offset + size];
+                *offset += size;
+                Ok(match scalar {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F64 => {
+                        f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment square with colored corners
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+property uchar flags
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3 7
+0 2
+";
+
+    #[test]
+    fn ascii() {
+        let model = PlyModel::parse(ASCII.as_bytes()).unwrap();
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.vertices[1].color, Some(Color::rgb(0, 255, 0)));
+        assert_eq!(model.vertices[1].normal, None);
+        assert_eq!(model.triangles.len(), 2);
+
+        let first = &model.triangles[0];
+        assert_eq!(
+            (first.p1, first.p2, first.p3),
+            (
+                Point3::new(0., 0., 0.),
+                Point3::new(1., 1., 0.),
+                Point3::new(1., 0., 0.)
+            )
+        );
+        assert!(first.normal().z > 0.);
+        assert_eq!(
+            first.colors,
+            Some([
+                Color::rgb(255, 0, 0),
+                Color::rgb(0, 0, 255),
+                Color::rgb(0, 255, 0)
+            ])
+        );
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 3\n\
+            property double x\nproperty double y\nproperty double z\n\
+            property float nx\nproperty float ny\nproperty float nz\n\
+            property float red\nproperty float green\nproperty float blue\nproperty float alpha\n\
+            element face 1\nproperty list ushort uint vertex_index\nend_header\n"
+            .to_vec();
+        let points = [[0_f64, 0., 0.], [1., 0., 0.], [0., 1., 0.]];
+        for p in points.iter() {
+            for c in p.iter() {
+                bytes.extend_from_slice(&c.to_le_bytes());
+            }
+            for v in [0_f32, 0., 1., 1., 0.5, 0., 0.5].iter() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        bytes.extend_from_slice(&3_u16.to_le_bytes());
+        for i in [0_u32, 1, 2].iter() {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let model = PlyModel::parse(&bytes).unwrap();
+        let color = Color::new(255, 128, 0, 128);
+        let normal = Vector3::new(0., 0., 1.);
+        assert_eq!(
+            model.triangles,
+            vec![Triagnle3::new(
+                Point3::new(0., 0., 0.),
+                Point3::new(0., 1., 0.),
+                Point3::new(1., 0., 0.)
+            )
+            .with_colors([color, color, color])
+            .with_normals([normal, normal, normal])]
+        );
+
+        assert_eq!(
+            PlyModel::parse(&bytes[..bytes.len() - 1]),
+            Err(FormatError::binary(
+                bytes.len() - 4,
+                "unexpected end of the file"
+            ))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text: &str| PlyModel::parse(text.as_bytes()).unwrap_err();
+        assert_eq!(
+            error("ply\nformat binary_big_endian 1.0\nend_header\n"),
+            FormatError::Unsupported("format `binary_big_endian`".to_string())
+        );
+        assert_eq!(
+            error("ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n1\n"),
+            FormatError::Unsupported("vertex without `y`".to_string())
+        );
+        assert_eq!(
+            error("ply\nformat ascii 1.0\nproperty float x\nend_header\n"),
+            FormatError::syntax(3, "property before any element")
+        );
+        assert_eq!(
+            error(&ASCII.replace("1 1 0 0 0 255", "1 1 0 0 x 255")),
+            FormatError::syntax(20, "invalid number `x`")
+        );
+        assert_eq!(
+            error(&ASCII.replace("4 0 1 2 3 7", "4 0 1 2 9 7")),
+            FormatError::syntax(22, "invalid vertex index 9")
+        );
+        assert_eq!(
+            error(
+                "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\n\
+                   property float z\nend_header\n1 2 3\n"
+            ),
+            FormatError::Unsupported("model without faces".to_string())
+        );
+        assert_eq!(error("obj"), FormatError::syntax(1, "no `end_header`"));
+    }
+}
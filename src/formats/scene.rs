@@ -0,0 +1,551 @@
+//! Scenes saved as JSON.
+//!
+//! Schema, members marked optional may be left out, their default values are given after `=`:
+//!
+//! ```text
+//! {
+//!   "camera": {
+//!     "position": [x, y, z],
+//!     "target": [x, y, z],
+//!     "up": [x, y, z],
+//!     "width": 1200, "height": 800,          viewport in pixels, at most 4096
+//!     "fov": 1.05,                           optional = pi / 3, vertical field of view in radians
+//!     "near": 1, "far": 10000,               optional = 1 and 10000, clip planes
+//!     "projection": "perspective"            optional = "perspective", or "orthographic"
+//!   },
+//!   "shapes": [
+//!     {
+//!       "triangles": [
+//!         {
+//!           "points": [[x, y, z], [x, y, z], [x, y, z]],
+//!           "normals": [[x, y, z], [x, y, z], [x, y, z]],      optional, normals at the vertices
+//!           "uvs": [[u, v], [u, v], [u, v]],                   optional, texture coordinates
+//!           "colors": [[r, g, b, a], [r, g, b, a], [r, g, b, a]]  optional, vertex colors 0-255
+//!         }
+//!       ],
+//!       "transform": {                       optional = identity
+//!         "axis_point": [x, y, z], "axis_vector": [x, y, z], "angle": 0, "intersept": [x, y, z]
+//!       },
+//!       "speeds": {"rotation_speed": 0, "move_speed": 0},      optional = still
+//!       "material": {                        optional = default material, members are optional too
+//!         "fill": [r, g, b, a], "stroke": [r, g, b, a],
+//!         "opacity": 1, "stroke_width": 1, "edges_visible": true
+//!       },
+//!       "cull_mode": "none"                  optional = "none", or "back" or "front"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Triangles are the ones of the shape before its transform, a shape with a transform
+//! or speeds is loaded as `Animated` and a shape without them as `SetOfTriangles`.
+//! Textures, lights and modes of the scene are not saved. Ids of shapes are not saved either,
+//! loaded shapes get ids from 0 in the order of the file like when they are added one by one.
+
+use super::json::Json;
+use super::FormatError;
+use crate::geometry::camera::{Camera, Projection};
+use crate::geometry::color::Color;
+use crate::geometry::g2d::Point2;
+use crate::geometry::g3d::{Point3, Triagnle3};
+use crate::geometry::material::Material;
+use crate::geometry::{Animated, Animation, CullMode, SceneTmp, SetOfTriangles, Shape, Transform};
+
+/// Biggest width and height of the viewport, frame buffers of the size are allocated
+/// when the scene is loaded and take 12 bytes per pixel with the depth
+const MAX_VIEWPORT: usize = 4096;
+
+pub fn to_json(scene: &SceneTmp) -> Json {
+    let shapes = scene
+        .shape_ids()
+        .into_iter()
+        .filter_map(|id| {
+            let shape = scene.shape(id)?;
+            let mut members = Vec::new();
+            let triangles = match shape.placement() {
+                Some(_) => shape.untransformed(),
+                None => shape.approximate(),
+            };
+            members.push(("triangles", triangles.iter().map(triangle).collect()));
+            if let Some((t, a)) = shape.placement() {
+                members.push((
+                    "transform",
+                    object(vec![
+                        ("axis_point", point(&t.axis_point)),
+                        ("axis_vector", point(&t.axis_vector)),
+                        ("angle", t.angle.into()),
+                        ("intersept", point(&t.intersept)),
+                    ]),
+                ));
+                members.push((
+                    "speeds",
+                    object(vec![
+                        ("rotation_speed", a.rotation_speed.into()),
+                        ("move_speed", a.move_speed.into()),
+                    ]),
+                ));
+            }
+            if let Some(m) = scene.material(id) {
+                members.push((
+                    "material",
+                    object(vec![
+                        ("fill", color(&m.fill)),
+                        ("stroke", color(&m.stroke)),
+                        ("opacity", m.opacity.into()),
+                        ("stroke_width", m.stroke_width.into()),
+                        ("edges_visible", m.edges_visible.into()),
+                    ]),
+                ));
+            }
+            let cull_mode = match scene.cull_mode(id)? {
+                CullMode::None => "none",
+                CullMode::Back => "back",
+                CullMode::Front => "front",
+            };
+            members.push(("cull_mode", cull_mode.into()));
+            Some(object(members))
+        })
+        .collect();
+    object(vec![("camera", camera(scene.camera())), ("shapes", shapes)])
+}
+
+pub fn from_json(text: &str) -> Result<SceneTmp, FormatError> {
+    let json = Json::parse(text)?;
+    let reader = Reader {
+        json: &json,
+        path: String::new(),
+    };
+    let mut scene = SceneTmp::new(reader.member("camera")?.camera()?);
+    for shape in reader.member("shapes")?.items()? {
+        let triangles = shape
+            .member("triangles")?
+            .items()?
+            .iter()
+            .map(Reader::triangle)
+            .collect::<Result<Vec<_>, _>>()?;
+        let triangles = SetOfTriangles::new(triangles);
+        let transform = shape
+            .optional("transform")
+            .map(|t| t.transform())
+            .transpose()?;
+        let speeds = shape.optional("speeds").map(|s| s.speeds()).transpose()?;
+        let boxed: Box<dyn Shape> = match (transform, speeds) {
+            (None, None) => Box::new(triangles),
+            (transform, speeds) => Box::new(Animated::new(
+                triangles,
+                transform.unwrap_or_else(Transform::identity),
+                speeds.unwrap_or_else(Animation::still),
+            )),
+        };
+        let id = scene.add_shape(boxed);
+
+        if let Some(material) = shape.optional("material") {
+            let m = scene.material_mut(id).unwrap();
+            material.update_material(m)?;
+        }
+        if let Some(cull_mode) = shape.optional("cull_mode") {
+            let cull_mode = match cull_mode.string()? {
+                "none" => CullMode::None,
+                "back" => CullMode::Back,
+                "front" => CullMode::Front,
+                _ => return Err(cull_mode.invalid()),
+            };
+            scene.set_cull_mode(id, cull_mode);
+        }
+    }
+    Ok(scene)
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn numbers(values: &[f64]) -> Json {
+    Json::Array(values.iter().map(|&v| Json::Number(v)).collect())
+}
+
+fn point(p: &Point3) -> Json {
+    numbers(&[p.x, p.y, p.z])
+}
+
+fn color(c: &Color) -> Json {
+    numbers(&[c.r as f64, c.g as f64, c.b as f64, c.a as f64])
+}
+
+fn triangle(t: &Triagnle3) -> Json {
+    let mut members = vec![(
+        "points",
+        Json::Array(vec![point(&t.p1), point(&t.p2), point(&t.p3)]),
+    )];
+    if let Some(normals) = &t.normals {
+        members.push(("normals", normals.iter().map(point).collect()));
+    }
+    if let Some(uvs) = &t.uvs {
+        members.push(("uvs", uvs.iter().map(|uv| numbers(&[uv.x, uv.y])).collect()));
+    }
+    if let Some(colors) = &t.colors {
+        members.push(("colors", colors.iter().map(color).collect()));
+    }
+    object(members)
+}
+
+fn camera(c: &Camera) -> Json {
+    let (width, height) = c.viewport();
+    let (near, far) = c.clip_planes();
+    let projection = match c.projection() {
+        Projection::Perspective => "perspective",
+        Projection::Orthographic => "orthographic",
+    };
+    object(vec![
+        ("position", point(&c.position())),
+        ("target", point(&c.target())),
+        ("up", point(&c.up())),
+        ("width", (width as f64).into()),
+        ("height", (height as f64).into()),
+        ("fov", c.fov().into()),
+        ("near", near.into()),
+        ("far", far.into()),
+        ("projection", projection.into()),
+    ])
+}
+
+/// JSON value together with its path in the document, like `shapes[0].material`,
+/// which is used in errors
+struct Reader<'a> {
+    json: &'a Json,
+    path: String,
+}
+
+impl<'a> Reader<'a> {
+    fn invalid(&self) -> FormatError {
+        FormatError::Invalid(format!("invalid `{}`", self.path))
+    }
+
+    fn optional(&self, key: &str) -> Option<Reader<'a>> {
+        let path = if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        };
+        self.json.get(key).map(|json| Reader { json, path })
+    }
+
+    fn member(&self, key: &str) -> Result<Reader<'a>, FormatError> {
+        self.optional(key).ok_or_else(|| {
+            FormatError::Invalid(match self.path.as_str() {
+                "" => format!("no `{}`", key),
+                path => format!("no `{}` in `{}`", key, path),
+            })
+        })
+    }
+
+    fn items(&self) -> Result<Vec<Reader<'a>>, FormatError> {
+        let items = self.json.as_array().ok_or_else(|| self.invalid())?;
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(idx, json)| Reader {
+                json,
+                path: format!("{}[{}]", self.path, idx),
+            })
+            .collect())
+    }
+
+    fn number(&self) -> Result<f64, FormatError> {
+        self.json.as_f64().ok_or_else(|| self.invalid())
+    }
+
+    fn string(&self) -> Result<&'a str, FormatError> {
+        self.json.as_str().ok_or_else(|| self.invalid())
+    }
+
+    fn numbers(&self, count: usize) -> Result<Vec<f64>, FormatError> {
+        self.json
+            .as_numbers()
+            .filter(|n| n.len() == count)
+            .ok_or_else(|| self.invalid())
+    }
+
+    fn point(&self) -> Result<Point3, FormatError> {
+        let n = self.numbers(3)?;
+        Ok(Point3::new(n[0], n[1], n[2]))
+    }
+
+    fn color(&self) -> Result<Color, FormatError> {
+        let n = self.numbers(4)?;
+        if n.iter().any(|c| *c < 0. || *c > 255. || c.fract() != 0.) {
+            return Err(self.invalid());
+        }
+        Ok(Color::new(n[0] as u8, n[1] as u8, n[2] as u8, n[3] as u8))
+    }
+
+    /// Three values read by `read`
+    fn three<T, F: Fn(&Reader<'a>) -> Result<T, FormatError>>(
+        &self,
+        read: F,
+    ) -> Result<[T; 3], FormatError> {
+        let items = self.items()?;
+        if items.len() != 3 {
+            return Err(self.invalid());
+        }
+        Ok([read(&items[0])?, read(&items[1])?, read(&items[2])?])
+    }
+
+    fn triangle(&self) -> Result<Triagnle3, FormatError> {
+        let [p1, p2, p3] = self.member("points")?.three(Reader::point)?;
+        let mut triangle = Triagnle3::new(p1, p2, p3);
+        if let Some(normals) = self.optional("normals") {
+            triangle = triangle.with_normals(normals.three(Reader::point)?);
+        }
+        if let Some(uvs) = self.optional("uvs") {
+            triangle = triangle.with_uvs(uvs.three(|uv| {
+                let n = uv.numbers(2)?;
+                Ok(Point2::new(n[0], n[1]))
+            })?);
+        }
+        if let Some(colors) = self.optional("colors") {
+            triangle = triangle.with_colors(colors.three(Reader::color)?);
+        }
+        Ok(triangle)
+    }
+
+    fn transform(&self) -> Result<Transform, FormatError> {
+        Ok(Transform::new(
+            self.member("axis_point")?.point()?,
+            self.member("axis_vector")?.point()?,
+            self.member("angle")?.number()?,
+            self.member("intersept")?.point()?,
+        ))
+    }
+
+    fn speeds(&self) -> Result<Animation, FormatError> {
+        Ok(Animation::new(
+            self.member("rotation_speed")?.number()?,
+            self.member("move_speed")?.number()?,
+        ))
+    }
+
+    fn update_material(&self, m: &mut Material) -> Result<(), FormatError> {
+        if let Some(fill) = self.optional("fill") {
+            m.fill = fill.color()?;
+        }
+        if let Some(stroke) = self.optional("stroke") {
+            m.stroke = stroke.color()?;
+        }
+        if let Some(opacity) = self.optional("opacity") {
+            m.opacity = opacity.number()?;
+        }
+        if let Some(width) = self.optional("stroke_width") {
+            m.stroke_width = width.number()?;
+        }
+        if let Some(visible) = self.optional("edges_visible") {
+            m.edges_visible = visible.json.as_bool().ok_or_else(|| visible.invalid())?;
+        }
+        Ok(())
+    }
+
+    fn camera(&self) -> Result<Camera, FormatError> {
+        let size = |key: &str| {
+            let member = self.member(key)?;
+            match member.json.as_usize() {
+                Some(n) if n > MAX_VIEWPORT => Err(FormatError::Invalid(format!(
+                    "`{}` is bigger than {}",
+                    member.path, MAX_VIEWPORT
+                ))),
+                Some(n) if n > 0 => Ok(n as i32),
+                _ => Err(member.invalid()),
+            }
+        };
+        let mut camera = Camera::new(
+            self.member("position")?.point()?,
+            self.member("target")?.point()?,
+            self.member("up")?.point()?,
+            size("width")?,
+            size("height")?,
+        );
+        if let Some(fov) = self.optional("fov") {
+            camera.set_fov(fov.number()?);
+        }
+        let (mut near, mut far) = camera.clip_planes();
+        if let Some(n) = self.optional("near") {
+            near = n.number()?;
+        }
+        if let Some(f) = self.optional("far") {
+            far = f.number()?;
+        }
+        camera.set_clip_planes(near, far);
+        if let Some(projection) = self.optional("projection") {
+            camera.set_projection(match projection.string()? {
+                "perspective" => Projection::Perspective,
+                "orthographic" => Projection::Orthographic,
+                _ => return Err(projection.invalid()),
+            });
+        }
+        Ok(camera)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::geometry::g3d::Vector3;
+
+    fn scene() -> SceneTmp {
+        let mut camera = Camera::new(
+            Point3::new(0., 0., 500.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., -1., 0.),
+            300,
+            200,
+        );
+        camera.set_projection(Projection::Orthographic);
+        camera.set_fov(0.5);
+        let mut scene = SceneTmp::new(camera);
+
+        let triangle = Triagnle3::new(
+            Point3::new(0.1, 0., 0.),
+            Point3::new(10., 0., 0.),
+            Point3::new(0., 10., 1. / 3.),
+        );
+        let normal = Vector3::new(0., 0., 1.);
+        let animated = scene.add_shape(Box::new(Animated::new(
+            SetOfTriangles::new(vec![
+                triangle.clone(),
+                triangle
+                    .clone()
+                    .with_normals([normal, normal, normal])
+                    .with_uvs([
+                        Point2::new(0., 0.),
+                        Point2::new(1., 0.),
+                        Point2::new(0., 1.),
+                    ])
+                    .with_colors([
+                        Color::rgb(1, 2, 3),
+                        Color::rgb(4, 5, 6),
+                        Color::new(7, 8, 9, 10),
+                    ]),
+            ]),
+            Transform::new(
+                Point3::new(1., 2., 3.),
+                Vector3::new(0., 1., 0.),
+                0.25,
+                Vector3::new(5., 0., 0.),
+            ),
+            Animation::new(0.01, 0.5),
+        )));
+        let material = scene.material_mut(animated).unwrap();
+        material.fill = Color::new(10, 20, 30, 40);
+        material.opacity = 0.75;
+        material.edges_visible = false;
+        scene.set_cull_mode(animated, CullMode::Back);
+
+        scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle])));
+        scene
+    }
+
+    #[test]
+    fn round_trip() {
+        let scene = scene();
+        let text = to_json(&scene).to_string();
+        let loaded = from_json(&text).unwrap();
+        assert_eq!(to_json(&loaded).to_string(), text);
+
+        for id in scene.shape_ids() {
+            assert_eq!(
+                loaded.shape(id).unwrap().approximate(),
+                scene.shape(id).unwrap().approximate()
+            );
+            assert_eq!(loaded.material(id), scene.material(id));
+            assert_eq!(loaded.cull_mode(id), scene.cull_mode(id));
+        }
+        assert!(loaded.shape(1).unwrap().placement().is_none());
+        assert_eq!(loaded.camera().viewport(), (300, 200));
+        assert_eq!(loaded.camera().projection(), Projection::Orthographic);
+        assert_eq!(loaded.camera().fov(), 0.5);
+    }
+
+    #[test]
+    fn ids_are_renumbered() {
+        let mut scene = scene();
+        let last = scene.add_shape(Box::new(SetOfTriangles::new(
+            scene.shape(1).unwrap().approximate(),
+        )));
+        scene.remove_shape(1);
+        let text = to_json(&scene).to_string();
+        let loaded = from_json(&text).unwrap();
+        assert_eq!(scene.shape_ids(), vec![0, last]);
+        assert_eq!(loaded.shape_ids(), vec![0, 1]);
+        assert_eq!(loaded.material(0), scene.material(0));
+        assert_eq!(loaded.cull_mode(0), scene.cull_mode(0));
+        assert_eq!(
+            loaded.shape(1).unwrap().approximate(),
+            scene.shape(last).unwrap().approximate()
+        );
+        assert_eq!(to_json(&loaded).to_string(), text);
+    }
+
+    #[test]
+    fn defaults() {
+        let scene = from_json(
+            r#"{
+  "camera": {"position": [0, 0, 100], "target": [0, 0, 0], "up": [0, 1, 0], "width": 10, "height": 10},
+  "shapes": [{
+    "triangles": [{"points": [[0, 0, 0], [1, 0, 0], [0, 1, 0]]}],
+    "speeds": {"rotation_speed": 0.1, "move_speed": 0},
+    "material": {"stroke_width": 3}
+  }]
+}"#,
+        )
+        .unwrap();
+        let (transform, animation) = scene.shape(0).unwrap().placement().unwrap();
+        assert_eq!(*transform, Transform::identity());
+        assert_eq!(*animation, Animation::new(0.1, 0.));
+        let material = scene.material(0).unwrap();
+        assert_eq!(material.stroke_width, 3.);
+        assert_eq!(material.fill, Material::default().fill);
+        assert_eq!(scene.cull_mode(0), Some(CullMode::None));
+        assert_eq!(scene.camera().projection(), Projection::Perspective);
+    }
+
+    #[test]
+    fn errors() {
+        let text = to_json(&scene()).to_string();
+        assert_eq!(
+            from_json(&text.replace(r#""cull_mode":"back""#, r#""cull_mode":"both""#)).err(),
+            Some(FormatError::Invalid(
+                "invalid `shapes[0].cull_mode`".to_string()
+            ))
+        );
+        assert_eq!(
+            from_json(&text.replace(r#""fill":[10,20,30,40]"#, r#""fill":[10,20,300,40]"#)).err(),
+            Some(FormatError::Invalid(
+                "invalid `shapes[0].material.fill`".to_string()
+            ))
+        );
+        assert_eq!(
+            from_json(&text.replace(r#""angle""#, r#""turn""#)).err(),
+            Some(FormatError::Invalid(
+                "no `angle` in `shapes[0].transform`".to_string()
+            ))
+        );
+        assert_eq!(
+            from_json(&text.replace(r#""width":300"#, r#""width":100000"#)).err(),
+            Some(FormatError::Invalid(
+                "`camera.width` is bigger than 4096".to_string()
+            ))
+        );
+        assert_eq!(
+            from_json(r#"{"shapes": []}"#).err(),
+            Some(FormatError::Invalid("no `camera`".to_string()))
+        );
+        assert_eq!(
+            from_json("{").err(),
+            Some(FormatError::syntax(1, "expected the name of a member"))
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! STL models in the ASCII and the binary forms
+
+use std::fs;
+use std::path::Path;
+
+use super::FormatError;
+use crate::geometry::g3d::{Point3, Triagnle3, Vector3};
+
+const HEADER_LEN: usize = 80;
+const FACET_LEN: usize = 50;
+
+/// Triangles of the model with the normal of their facet at every vertex,
+/// facets with zero normal are left without normals, so the ones of the triangles are used.
+/// Vertices of facets go counter clockwise when looked at from the outside,
+/// so their order is reversed to make `Triagnle3::normal` point outside
+pub fn parse(bytes: &[u8]) -> Result<Vec<Triagnle3>, FormatError> {
+    // binary files may start with "solid" too, but their size is known from the header
+    // and text files don't have zero bytes
+    if bytes.starts_with(b"solid") && !has_binary_size(bytes) && !bytes.contains(&0) {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|e| FormatError::binary(e.valid_up_to(), "ASCII STL is not valid text"))?;
+        parse_ascii(text)
+    } else {
+        parse_binary(bytes)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Vec<Triagnle3>, FormatError> {
+    parse(&fs::read(path)?)
+}
+
+fn has_binary_size(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_LEN + 4 && binary_size(facets(bytes)) == Some(bytes.len())
+}
+
+/// Size of a binary file with `count` facets, None if it doesn't fit into usize
+/// (the count is read from the file and usize has 32 bits in wasm)
+fn binary_size(count: usize) -> Option<usize> {
+    FACET_LEN.checked_mul(count)?.checked_add(HEADER_LEN + 4)
+}
+
+fn facets(bytes: &[u8]) -> usize {
+    read_u32(bytes, HEADER_LEN) as usize
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn read_vector(bytes: &[u8], offset: usize) -> Vector3 {
+    let f = |i: usize| f32::from_bits(read_u32(bytes, offset + i * 4)) as f64;
+    Vector3::new(f(0), f(1), f(2))
+}
+
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Triagnle3>, FormatError> {
+    if bytes.len() < HEADER_LEN + 4 {
+        return Err(FormatError::binary(bytes.len(), "header is too short"));
+    }
+    let count = facets(bytes);
+    let expected = binary_size(count)
+        .ok_or_else(|| FormatError::binary(HEADER_LEN, format!("{} facets are too many", count)))?;
+    if bytes.len() < expected {
+        return Err(FormatError::binary(
+            bytes.len(),
+            format!("{} facets need {} bytes", count, expected),
+        ));
+    }
+
+    Ok((0..count)
+        .map(|i| {
+            let offset = HEADER_LEN + 4 + FACET_LEN * i;
+            // the normal of the facet goes before its vertices
+            let normal = read_vector(bytes, offset);
+            let points = [1, 2, 3].map(|k| read_vector(bytes, offset + 12 * k));
+            triangle(&points, normal)
+        })
+        .collect())
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<Triagnle3>, FormatError> {
+    let mut tokens = Tokens::new(text);
+    tokens.expect("solid")?;
+    // name of the solid takes the rest of the line
+    tokens.skip_line();
+
+    let mut triangles = Vec::new();
+    loop {
+        match tokens.next() {
+            Some((_, "facet")) => {}
+            Some((_, "endsolid")) => break,
+            Some((line, token)) => {
+                return Err(FormatError::syntax(
+                    line,
+                    format!("expected `facet`, found `{}`", token),
+                ))
+            }
+            None => return Err(tokens.unexpected_end()),
+        }
+        tokens.expect("normal")?;
+        let normal = tokens.vector()?;
+        tokens.expect("outer")?;
+        tokens.expect("loop")?;
+        let mut points = Vec::new();
+        while tokens.peek() == Some("vertex") {
+            tokens.next();
+            points.push(tokens.vector()?);
+        }
+        if points.len() < 3 {
+            return Err(FormatError::syntax(
+                tokens.line,
+                "facet needs at least 3 vertices",
+            ));
+        }
+        tokens.expect("endloop")?;
+        tokens.expect("endfacet")?;
+        // polygons are not allowed by the format, but they are easy to read anyway
+        for i in 1..points.len() - 1 {
+            triangles.push(triangle(&[points[0], points[i], points[i + 1]], normal));
+        }
+    }
+    Ok(triangles)
+}
+
+/// Triangle with the reversed order of vertices and the normal of the facet if it is not zero
+fn triangle(points: &[Point3; 3], normal: Vector3) -> Triagnle3 {
+    let triangle = Triagnle3::new(points[0], points[2], points[1]);
+    if normal.length() == 0. {
+        triangle
+    } else {
+        triangle.with_normals([normal, normal, normal])
+    }
+}
+
+/// Words of the text with their line numbers
+struct Tokens<'a> {
+    words: std::iter::Peekable<Box<dyn Iterator<Item = (usize, &'a str)> + 'a>>,
+    /// line of the last token
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(text: &'a str) -> Self {
+        let words: Box<dyn Iterator<Item = (usize, &'a str)>> = Box::new(
+            text.lines()
+                .enumerate()
+                .flat_map(|(idx, line)| line.split_whitespace().map(move |w| (idx + 1, w))),
+        );
+        Tokens {
+            words: words.peekable(),
+            line: 1,
+        }
+    }
+
+    fn next(&mut self) -> Option<(usize, &'a str)> {
+        let token = self.words.next();
+        if let Some((line, _)) = token {
+            self.line = line;
+        }
+        token
+    }
+
+    fn peek(&mut self) -> Option<&'a str> {
+        self.words.peek().map(|(_, w)| *w)
+    }
+
+    fn skip_line(&mut self) {
+        let line = self.line;
+        while let Some((l, _)) = self.words.peek() {
+            if *l != line {
+                break;
+            }
+            self.words.next();
+        }
+    }
+
+    fn unexpected_end(&self) -> FormatError {
+        FormatError::syntax(self.line, "unexpected end of the file")
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), FormatError> {
+        match self.next() {
+            Some((_, token)) if token == expected => Ok(()),
+            Some((line, token)) => Err(FormatError::syntax(
+                line,
+                format!("expected `{}`, found `{}`", expected, token),
+            )),
+            None => Err(self.unexpected_end()),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, FormatError> {
+        match self.next() {
+            Some((line, token)) => token
+                .parse()
+                .map_err(|_| FormatError::syntax(line, format!("invalid number `{}`", token))),
+            None => Err(self.unexpected_end()),
+        }
+    }
+
+    fn vector(&mut self) -> Result<Vector3, FormatError> {
+        Ok(Vector3::new(self.number()?, self.number()?, self.number()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ASCII: &str = "solid two facets
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 0
+    outer loop
+      vertex 0 0 1
+      vertex 1 0 1
+      vertex 0 1 1
+    endloop
+  endfacet
+endsolid two facets
+";
+
+    fn binary(facets: &[[f32; 12]]) -> Vec<u8> {
+        let mut bytes = b"solid but binary".to_vec();
+        bytes.resize(HEADER_LEN, 0);
+        bytes.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for facet in facets.iter() {
+            for value in facet.iter() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn ascii() {
+        let triangles = parse(ASCII.as_bytes()).unwrap();
+        assert_eq!(triangles.len(), 2);
+        let normal = Vector3::new(0., 0., 1.);
+        assert_eq!(
+            triangles[0],
+            Triagnle3::new(
+                Point3::new(0., 0., 0.),
+                Point3::new(0., 1., 0.),
+                Point3::new(1., 0., 0.),
+            )
+            .with_normals([normal, normal, normal])
+        );
+        assert!(triangles[0].normal().dot_product(&normal) > 0.);
+        // zero normal of the second facet is not kept
+        assert_eq!(
+            triangles[1],
+            Triagnle3::new(
+                Point3::new(0., 0., 1.),
+                Point3::new(0., 1., 1.),
+                Point3::new(1., 0., 1.),
+            )
+        );
+    }
+
+    #[test]
+    fn binary_with_solid_header() {
+        let bytes = binary(&[[0., 0., -1., 0., 0., 0., 0., 1., 0., 1., 0., 0.]]);
+        let triangles = parse(&bytes).unwrap();
+        assert_eq!(triangles.len(), 1);
+        assert_eq!(triangles[0].p2, Point3::new(1., 0., 0.));
+        assert!(triangles[0].normal().z < 0.);
+        assert_eq!(triangles[0].normals, Some([Point3::new(0., 0., -1.); 3]));
+    }
+
+    #[test]
+    fn errors() {
+        let bytes = binary(&[[0.; 12], [0.; 12]]);
+        assert_eq!(
+            parse(&bytes[..bytes.len() - 1]),
+            Err(FormatError::binary(183, "2 facets need 184 bytes"))
+        );
+        assert_eq!(
+            parse(&[0; 10]),
+            Err(FormatError::binary(10, "header is too short"))
+        );
+        // the size of the count doesn't fit into 32 bits
+        let mut huge = bytes.clone();
+        huge[HEADER_LEN..HEADER_LEN + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(parse(&huge), Err(FormatError::Binary { .. })));
+        assert_eq!(
+            parse(ASCII.replace("vertex 1 0 1", "vertex 1 x 1").as_bytes()),
+            Err(FormatError::syntax(12, "invalid number `x`"))
+        );
+        assert_eq!(
+            parse(ASCII.replace("endloop", "end").as_bytes()),
+            Err(FormatError::syntax(7, "expected `endloop`, found `end`"))
+        );
+        assert_eq!(
+            parse(b"solid\n facet normal 0 0 1\n outer loop\n vertex 0 0 0\n"),
+            Err(FormatError::syntax(4, "facet needs at least 3 vertices"))
+        );
+        assert_eq!(
+            parse(b"solid cut\n facet normal 0 0"),
+            Err(FormatError::syntax(2, "unexpected end of the file"))
+        );
+    }
+}
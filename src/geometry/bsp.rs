@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+
+use super::camera::Camera;
+use super::g3d::Plane;
+use super::Face;
+
+/// Binary space partitioning tree, triangles crossing planes of other triangles are split.
+/// Once built it gives the order of painting for any position of the camera.
+/// Faces of convex meshes all lie on one side of each other, so the depth of the tree
+/// may be as big as the number of faces: nodes are kept in a vector and both
+/// building and traversal use their own stacks instead of recursion
+pub struct BspTree {
+    /// the first node is the root
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    plane: Plane,
+    /// faces lying on the plane
+    faces: Vec<Face>,
+    /// indices of the children in `BspTree::nodes`
+    front: Option<usize>,
+    back: Option<usize>,
+}
+
+/// Step of the traversal of the tree
+enum Visit {
+    /// subtree with the root at the index
+    Node(usize),
+    /// faces of the node at the index
+    Faces(usize),
+}
+
+impl BspTree {
+    pub fn new(faces: Vec<Face>) -> Self {
+        let faces: Vec<Face> = faces
+            .into_iter()
+            .filter(|f| !Plane::from_triangle(&f.triangle).is_degenerate())
+            .collect();
+
+        let mut nodes: Vec<Node> = Vec::new();
+        // faces of the nodes to build in the order of their indices,
+        // every non-empty list becomes a node, so the index is known when it is queued
+        let mut pending = VecDeque::new();
+        if !faces.is_empty() {
+            pending.push_back(faces);
+        }
+        while let Some(mut faces) = pending.pop_front() {
+            let splitter = faces.swap_remove(0);
+            let plane = Plane::from_triangle(&splitter.triangle);
+
+            let mut on_plane = vec![splitter];
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            for face in faces {
+                if plane.contains(&face.triangle) {
+                    on_plane.push(face);
+                } else {
+                    let (f, b) = face.split(&plane);
+                    front.extend(f);
+                    back.extend(b);
+                }
+            }
+
+            let mut child = |faces: Vec<Face>| {
+                if faces.is_empty() {
+                    return None;
+                }
+                pending.push_back(faces);
+                Some(nodes.len() + pending.len())
+            };
+            let front = child(front);
+            let back = child(back);
+            nodes.push(Node {
+                plane,
+                faces: on_plane,
+                front,
+                back,
+            });
+        }
+        BspTree { nodes }
+    }
+
+    /// Faces ordered from the farthest to the closest to the camera
+    pub fn back_to_front(&self, camera: &Camera) -> Vec<Face> {
+        let mut order = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(Visit::Node(0));
+        }
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Node(idx) => {
+                    let node = &self.nodes[idx];
+                    let (far, near) = if camera.is_in_front_of(&node.plane) {
+                        (node.back, node.front)
+                    } else {
+                        (node.front, node.back)
+                    };
+                    // the stack is popped in the reverse order
+                    stack.extend(near.map(Visit::Node));
+                    stack.push(Visit::Faces(idx));
+                    stack.extend(far.map(Visit::Node));
+                }
+                Visit::Faces(idx) => order.extend(self.nodes[idx].faces.iter().cloned()),
+            }
+        }
+        order
+    }
+}
@@ -0,0 +1,407 @@
+use super::g3d::{Plane, Point3, Triagnle3, Vector3};
+
+/// Camera can't get closer to the pole than this angle, otherwise the up vector is undefined
+const MIN_POLE_ANGLE: f64 = 0.01;
+
+/// Points of clipped triangles lie on the clip planes up to rounding errors,
+/// so the near and far planes are checked with this tolerance
+const CLIP_EPSILON: f64 = 1e-9;
+
+/// Signed distance to a clip plane in the clip coordinates, positive inside the frustum
+type ClipPlane<'a> = &'a dyn Fn(&[f64; 4]) -> f64;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Projection {
+    Perspective,
+    /// Parallel projection, the plane going through the target is shown
+    /// in the same scale as with the perspective projection
+    Orthographic,
+}
+
+/// Maps world coordinates to the screen coordinates of the viewport.
+/// Screen x goes to the right, screen y goes down and screen z is the depth:
+/// the bigger z is the closer point is to the viewer.
+/// With the perspective projection z is the inverse of the distance, so
+/// a triangle stays flat in the screen coordinates and can be compared by its plane.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    position: Point3,
+    target: Point3,
+    up: Vector3,
+    /// vertical field of view in radians
+    fov: f64,
+    near: f64,
+    far: f64,
+    width: i32,
+    height: i32,
+    projection: Projection,
+}
+
+impl Camera {
+    pub fn new(position: Point3, target: Point3, up: Vector3, width: i32, height: i32) -> Self {
+        Camera {
+            position,
+            target,
+            up,
+            fov: std::f64::consts::PI / 3.,
+            near: 1.,
+            far: 10000.,
+            width,
+            height,
+            projection: Projection::Perspective,
+        }
+    }
+
+    pub fn position(&self) -> Point3 {
+        self.position
+    }
+
+    pub fn up(&self) -> Vector3 {
+        self.up
+    }
+
+    pub fn fov(&self) -> f64 {
+        self.fov
+    }
+
+    /// Distances to the near and the far clip planes
+    pub fn clip_planes(&self) -> (f64, f64) {
+        (self.near, self.far)
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn set_fov(&mut self, fov: f64) {
+        self.fov = fov;
+    }
+
+    pub fn set_clip_planes(&mut self, near: f64, far: f64) {
+        self.near = near;
+        self.far = far;
+    }
+
+    pub fn set_viewport(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Width and height of the screen in pixels
+    pub fn viewport(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn target(&self) -> Point3 {
+        self.target
+    }
+
+    /// Moves the camera together with its target, so the view direction stays the same
+    pub fn set_target(&mut self, target: Point3) {
+        let offset = self.position.sub(&self.target);
+        self.target = target;
+        self.position = target.add(&offset);
+    }
+
+    /// Rotates the camera around its target.
+    /// `yaw` rotates around the up vector, `pitch` moves the camera towards the up vector
+    pub fn orbit(&mut self, yaw: f64, pitch: f64) {
+        let offset = self.position.sub(&self.target);
+        let radius = offset.length();
+        let up = self.up.normalize();
+
+        let height = offset.dot_product(&up);
+        let horizontal = offset.sub(&up.scale(height));
+        let a = if horizontal.length() > 0. {
+            horizontal.normalize()
+        } else {
+            self.basis().0
+        };
+        let b = up.x_product(&a);
+
+        let max_pitch = std::f64::consts::FRAC_PI_2 - MIN_POLE_ANGLE;
+        let pitch = ((height / radius).asin() + pitch).clamp(-max_pitch, max_pitch);
+        let direction = a.scale(yaw.cos()).add(&b.scale(yaw.sin()));
+        let offset = direction
+            .scale(radius * pitch.cos())
+            .add(&up.scale(radius * pitch.sin()));
+        self.position = self.target.add(&offset);
+    }
+
+    /// Multiplies the distance between the camera and its target by `factor`
+    pub fn zoom(&mut self, factor: f64) {
+        let offset = self.position.sub(&self.target);
+        let distance = offset.length();
+        let new_distance = (distance * factor).max(2. * self.near);
+        self.position = self.target.add(&offset.scale(new_distance / distance));
+    }
+
+    /// Moves the camera and its target parallel to the screen,
+    /// `dx` and `dy` are measured in pixels on the target plane
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        let (right, up, _) = self.basis();
+        let unit = 1. / self.scale(self.target_distance());
+        let shift = right.scale(-dx * unit).add(&up.scale(dy * unit));
+        self.position = self.position.add(&shift);
+        self.target = self.target.add(&shift);
+    }
+
+    /// Orthonormal basis of the camera: right, up and forward vectors.
+    /// Right vector points to the right side of the screen, up vector to the top
+    fn basis(&self) -> (Vector3, Vector3, Vector3) {
+        let forward = self.target.sub(&self.position).normalize();
+        let right = self.up.x_product(&forward).normalize();
+        let up = forward.x_product(&right);
+        (right, up, forward)
+    }
+
+    fn target_distance(&self) -> f64 {
+        self.target.sub(&self.position).length()
+    }
+
+    /// Number of pixels per world unit at the distance `depth` from the camera
+    fn scale(&self, depth: f64) -> f64 {
+        (self.height as f64 / 2.) / (depth * (self.fov / 2.).tan())
+    }
+
+    /// Whether the camera looks at the front side of the plane
+    pub fn is_in_front_of(&self, plane: &Plane) -> bool {
+        match self.projection {
+            Projection::Perspective => plane.distance(&self.position) > 0.,
+            Projection::Orthographic => {
+                let (_, _, forward) = self.basis();
+                plane.normal().dot_product(&forward) < 0.
+            }
+        }
+    }
+
+    /// Homogeneous clip coordinates (x, y, z, w) of the point.
+    /// The point is in the view frustum if -w <= x <= w, -w <= y <= w and near <= z <= far,
+    /// z is the distance from the camera along its direction.
+    /// w is z for the perspective projection and 1 for the orthographic one,
+    /// all of the coordinates are linear in the world coordinates of the point
+    pub fn to_clip(&self, p: &Point3) -> [f64; 4] {
+        let (right, up, forward) = self.basis();
+        let relative = p.sub(&self.position);
+        let depth = relative.dot_product(&forward);
+        let (half_width, half_height) = (self.width as f64 / 2., self.height as f64 / 2.);
+        let (scale, w) = match self.projection {
+            // scale at the distance 1
+            Projection::Perspective => (self.scale(1.), depth),
+            Projection::Orthographic => (self.scale(self.target_distance()), 1.),
+        };
+        [
+            relative.dot_product(&right) * scale / half_width,
+            relative.dot_product(&up) * scale / half_height,
+            depth,
+            w,
+        ]
+    }
+
+    /// Pieces of the triangle inside the view frustum in the world coordinates,
+    /// the triangle is cut by the six planes of the frustum in the clip coordinates
+    /// (see `to_clip`). Vertex attributes of the pieces are interpolated
+    pub fn clip(&self, triangle: &Triagnle3) -> Vec<Triagnle3> {
+        let (near, far) = (self.near, self.far);
+        self.clip_by(
+            triangle,
+            &[
+                &|c| c[2] - near,
+                &|c| far - c[2],
+                &|c| c[3] + c[0],
+                &|c| c[3] - c[0],
+                &|c| c[3] + c[1],
+                &|c| c[3] - c[1],
+            ],
+        )
+    }
+
+    /// Pieces of the triangle between the near and the far planes, all of their vertices
+    /// can be put on the screen. Parts outside of the sides of the viewport are kept
+    pub fn clip_depth(&self, triangle: &Triagnle3) -> Vec<Triagnle3> {
+        let (near, far) = (self.near, self.far);
+        self.clip_by(triangle, &[&|c| c[2] - near, &|c| far - c[2]])
+    }
+
+    /// Pieces of the triangle in front of all the planes
+    fn clip_by(&self, triangle: &Triagnle3, planes: &[ClipPlane]) -> Vec<Triagnle3> {
+        let mut pieces = vec![triangle.clone()];
+        for plane in planes.iter() {
+            pieces = pieces
+                .iter()
+                .flat_map(|t| t.split_by(|p| plane(&self.to_clip(p))).0)
+                .collect();
+        }
+        pieces
+    }
+
+    /// Returns None if the point is outside of the near and far planes
+    pub fn to_screen(&self, p: &Point3) -> Option<Point3> {
+        let (right, up, forward) = self.basis();
+        let relative = p.sub(&self.position);
+        let depth = relative.dot_product(&forward);
+        if depth < self.near - CLIP_EPSILON || depth > self.far + CLIP_EPSILON {
+            return None;
+        }
+
+        let (scale, z) = match self.projection {
+            Projection::Perspective => (self.scale(depth), 1. / depth),
+            Projection::Orthographic => (self.scale(self.target_distance()), -depth),
+        };
+        let x = self.width as f64 / 2. + relative.dot_product(&right) * scale;
+        let y = self.height as f64 / 2. - relative.dot_product(&up) * scale;
+        Some(Point3::new(x, y, z))
+    }
+
+    /// Returns None if any vertex of the triangle is outside of the near and far planes.
+    /// Vertex attributes stay the same
+    pub fn to_screen_triangle(&self, triangle: &Triagnle3) -> Option<Triagnle3> {
+        Some(triangle.with_points(
+            self.to_screen(&triangle.p1)?,
+            self.to_screen(&triangle.p2)?,
+            self.to_screen(&triangle.p3)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn camera() -> Camera {
+        Camera::new(
+            Point3::new(0., 0., 100.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., -1., 0.),
+            200,
+            200,
+        )
+    }
+
+    fn assert_close(left: Point3, right: Point3) {
+        assert!(
+            left.sub(&right).length() < 1e-9,
+            "{:?} != {:?}",
+            left,
+            right
+        );
+    }
+
+    #[test]
+    fn target_is_in_the_center() {
+        assert_close(
+            camera().to_screen(&Point3::new(0., 0., 0.)).unwrap(),
+            Point3::new(100., 100., 0.01),
+        );
+    }
+
+    #[test]
+    fn perspective() {
+        let camera = camera();
+        let near = camera.to_screen(&Point3::new(10., 10., 50.)).unwrap();
+        let far = camera.to_screen(&Point3::new(10., 10., -100.)).unwrap();
+        assert!(near.x > far.x && near.y > far.y && near.z > far.z);
+        assert_eq!(camera.to_screen(&Point3::new(0., 0., 200.)), None);
+    }
+
+    #[test]
+    fn orbit_keeps_distance_to_target() {
+        let mut camera = camera();
+        camera.orbit(std::f64::consts::FRAC_PI_2, 0.);
+        assert_close(camera.position, Point3::new(-100., 0., 0.));
+        // pitch is clamped near the pole
+        camera.orbit(0., std::f64::consts::PI);
+        assert_eq!(camera.target, Point3::new(0., 0., 0.));
+        assert!((camera.position.length() - 100.).abs() < 1e-9);
+        assert!(camera.position.y < -99.99 && camera.position.x < 0.);
+    }
+
+    #[test]
+    fn zoom_and_pan() {
+        let mut camera = camera();
+        camera.zoom(0.5);
+        assert_close(camera.position, Point3::new(0., 0., 50.));
+        camera.zoom(0.);
+        assert_close(camera.position, Point3::new(0., 0., 2.));
+
+        let mut camera = self::camera();
+        camera.pan(10., 0.);
+        let target = camera.to_screen(&Point3::new(0., 0., 0.)).unwrap();
+        assert!((target.x - 110.).abs() < 1e-9);
+        assert!(camera.target.x < 0.);
+        assert_eq!((camera.target.y, camera.target.z), (0., 0.));
+    }
+
+    #[test]
+    fn orthographic() {
+        let mut camera = camera();
+        camera.set_projection(Projection::Orthographic);
+        let near = camera.to_screen(&Point3::new(10., 10., 50.)).unwrap();
+        let far = camera.to_screen(&Point3::new(10., 10., -100.)).unwrap();
+        assert_eq!((near.x, near.y), (far.x, far.y));
+        assert!(near.z > far.z);
+    }
+
+    #[test]
+    fn clip_by_near_plane() {
+        let camera = camera();
+        // goes from in front of the camera to behind it
+        let triangle = Triagnle3::new(
+            Point3::new(-10., -10., 0.),
+            Point3::new(10., -10., 0.),
+            Point3::new(0., 0., 150.),
+        );
+        assert_eq!(camera.to_screen_triangle(&triangle), None);
+        let pieces = camera.clip(&triangle);
+        assert!(!pieces.is_empty());
+        for piece in pieces.iter() {
+            let screen = camera.to_screen_triangle(piece).unwrap();
+            for p in [screen.p1, screen.p2, screen.p3].iter() {
+                // 1 / near
+                assert!(p.z <= 1. + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn clip_by_sides() {
+        let mut camera = camera();
+        // half of the triangle is to the left of the screen
+        let triangle = Triagnle3::new(
+            Point3::new(0., -10., 0.),
+            Point3::new(0., 10., 0.),
+            Point3::new(-1000., 0., 0.),
+        )
+        .with_normals([
+            Vector3::new(0., 0., 1.),
+            Vector3::new(0., 0., 1.),
+            Vector3::new(1., 0., 0.),
+        ]);
+        for projection in [Projection::Perspective, Projection::Orthographic].iter() {
+            camera.set_projection(*projection);
+            let pieces = camera.clip(&triangle);
+            assert!(!pieces.is_empty());
+            for piece in pieces.iter() {
+                let screen = camera.to_screen_triangle(piece).unwrap();
+                for p in [screen.p1, screen.p2, screen.p3].iter() {
+                    assert!(p.x > -1e-9 && p.x < 200. + 1e-9);
+                }
+            }
+            // normals are interpolated on the cut edges
+            assert!(pieces
+                .iter()
+                .flat_map(|piece| piece.normals.unwrap().to_vec())
+                .any(|n| n.x > 0. && n.z > 0.));
+            // the part on the screen is kept whole
+            let visible = camera.clip_depth(&triangle);
+            assert_eq!(visible.len(), 1);
+        }
+
+        let outside = triangle.shift(&Vector3::new(-2000., 0., 0.));
+        assert!(camera.clip(&outside).is_empty());
+    }
+}
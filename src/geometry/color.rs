@@ -0,0 +1,40 @@
+/// RGBA color, 8 bits per channel
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color::new(r, g, b, 255)
+    }
+
+    pub fn transparent() -> Self {
+        Color::new(0, 0, 0, 0)
+    }
+
+    /// Weighted sum of the colors, weights are expected to add up to one
+    pub fn mix(colors: &[Color; 3], weights: [f64; 3]) -> Color {
+        let channel = |f: fn(&Color) -> u8| {
+            let sum: f64 = colors
+                .iter()
+                .zip(weights.iter())
+                .map(|(c, w)| f(c) as f64 * w)
+                .sum();
+            sum.round().clamp(0., 255.) as u8
+        };
+        Color::new(
+            channel(|c| c.r),
+            channel(|c| c.g),
+            channel(|c| c.b),
+            channel(|c| c.a),
+        )
+    }
+}
@@ -1,13 +1,11 @@
-use std::cmp;
-
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Point2 {
-    pub x: i32,
-    pub y: i32,
+    pub x: f64,
+    pub y: f64,
 }
 
 impl Point2 {
-    pub fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: f64, y: f64) -> Self {
         Point2 { x, y }
     }
 }
@@ -20,44 +18,44 @@ pub struct Segment<'a> {
 
 /// Represents infinite line
 /// A, B, C - coefficients in equation A * X + B * Y = C
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq)]
 pub struct Line {
-    A: i32,
-    B: i32,
-    C: i32,
+    a: f64,
+    b: f64,
+    c: f64,
 }
 
 impl Line {
-    pub fn new(A: i32, B: i32, C: i32) -> Self {
-        if A < 0 || (A == 0 && B < 0) {
+    pub fn new(a: f64, b: f64, c: f64) -> Self {
+        if a < 0. || (a == 0. && b < 0.) {
             Line {
-                A: -A,
-                B: -B,
-                C: -C,
+                a: -a,
+                b: -b,
+                c: -c,
             }
         } else {
-            Line { A, B, C }
+            Line { a, b, c }
         }
     }
 
     // https://stackoverflow.com/questions/20677795/how-do-i-compute-the-intersection-point-of-two-lines
     pub fn from_segment(seg: &Segment) -> Self {
-        let A = seg.p1.y - seg.p2.y;
-        let B = seg.p2.x - seg.p1.x;
-        let C = -(seg.p1.x * seg.p2.y - seg.p2.x * seg.p1.y);
-        Line::new(A, B, C)
+        let a = seg.p1.y - seg.p2.y;
+        let b = seg.p2.x - seg.p1.x;
+        let c = -(seg.p1.x * seg.p2.y - seg.p2.x * seg.p1.y);
+        Line::new(a, b, c)
     }
 
     /// todo: what if lines are the same
     pub fn intersection(&self, other: &Line) -> Option<Point2> {
-        let D = self.A * other.B - self.B * other.A;
-        let Dx = self.C * other.B - self.B * other.C;
-        let Dy = self.A * other.C - self.C * other.A;
-        if D == 0 {
+        let d = self.a * other.b - self.b * other.a;
+        let dx = self.c * other.b - self.b * other.c;
+        let dy = self.a * other.c - self.c * other.a;
+        if d == 0. {
             None
         } else {
-            let x = Dx / D;
-            let y = Dy / D;
+            let x = dx / d;
+            let y = dy / d;
             Some(Point2::new(x, y))
         }
     }
@@ -70,10 +68,10 @@ impl<'a> Segment<'a> {
 
     // checks whether point p belongs to the box bounded by p1 and p2
     fn is_point_in_box(&self, p: &Point2) -> bool {
-        let x1 = cmp::min(self.p1.x, self.p2.x);
-        let x2 = cmp::max(self.p1.x, self.p2.x);
-        let y1 = cmp::min(self.p1.y, self.p2.y);
-        let y2 = cmp::max(self.p1.y, self.p2.y);
+        let x1 = self.p1.x.min(self.p2.x);
+        let x2 = self.p1.x.max(self.p2.x);
+        let y1 = self.p1.y.min(self.p2.y);
+        let y2 = self.p1.y.max(self.p2.y);
         x1 <= p.x && p.x <= x2 && y1 <= p.y && p.y <= y2
     }
 
@@ -81,16 +79,16 @@ impl<'a> Segment<'a> {
     /// todo: edge cases
     ///     * two segments on the same line
     pub fn intersection(&self, other: &Segment) -> Option<Point2> {
-        let l1 = Line::from_segment(&self);
-        let l2 = Line::from_segment(&other);
+        let l1 = Line::from_segment(self);
+        let l2 = Line::from_segment(other);
         if l1 == l2 {
-            if self.is_point_in_box(&other.p1) {
+            if self.is_point_in_box(other.p1) {
                 Some(*other.p1)
-            } else if self.is_point_in_box(&other.p2) {
+            } else if self.is_point_in_box(other.p2) {
                 Some(*other.p2)
-            } else if other.is_point_in_box(&self.p1) {
+            } else if other.is_point_in_box(self.p1) {
                 Some(*self.p1)
-            } else if other.is_point_in_box(&self.p2) {
+            } else if other.is_point_in_box(self.p2) {
                 Some(*self.p2)
             } else {
                 None
@@ -102,7 +100,50 @@ impl<'a> Segment<'a> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// Rectangle with sides parallel to the axes
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Rect {
+    pub min: Point2,
+    pub max: Point2,
+}
+
+impl Rect {
+    pub fn new(min: Point2, max: Point2) -> Self {
+        Rect { min, max }
+    }
+
+    /// Rectangles touching each other overlap
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
+
+/// Pairs of indexes (i < j) of overlapping rectangles.
+/// Sweep and prune: rectangles are sorted by their left sides and every rectangle is
+/// compared only with the rectangles which still overlap it along x
+pub fn overlapping_pairs(rects: &[Rect]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| rects[a].min.x.partial_cmp(&rects[b].min.x).unwrap());
+
+    let mut pairs = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for &idx in order.iter() {
+        let rect = &rects[idx];
+        active.retain(|&other| rects[other].max.x >= rect.min.x);
+        for &other in active.iter() {
+            if rect.overlaps(&rects[other]) {
+                pairs.push((idx.min(other), idx.max(other)));
+            }
+        }
+        active.push(idx);
+    }
+    pairs
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Triangle2 {
     pub p1: Point2,
     pub p2: Point2,
@@ -116,9 +157,9 @@ impl Triangle2 {
 
     fn sign(p1: &Point2, p2: &Point2, p3: &Point2) -> i8 {
         let ind = (p1.x - p3.x) * (p2.y - p3.y) - (p2.x - p3.x) * (p1.y - p3.y);
-        if ind > 0 {
+        if ind > 0. {
             1
-        } else if ind < 0 {
+        } else if ind < 0. {
             -1
         } else {
             0
@@ -165,6 +206,103 @@ impl Triangle2 {
         }
         None
     }
+
+    pub fn bounding_box(&self) -> Rect {
+        let points = [self.p1, self.p2, self.p3];
+        let min = |f: fn(&Point2) -> f64| points.iter().map(f).fold(f64::INFINITY, f64::min);
+        let max = |f: fn(&Point2) -> f64| points.iter().map(f).fold(f64::NEG_INFINITY, f64::max);
+        Rect::new(
+            Point2::new(min(|p| p.x), min(|p| p.y)),
+            Point2::new(max(|p| p.x), max(|p| p.y)),
+        )
+    }
+
+    /// Pieces of the triangle inside the rectangle.
+    /// The triangle is clipped by every side of the rectangle in turn (Sutherland–Hodgman)
+    /// and the convex polygon left is cut into a fan of triangles, which keep the order
+    /// of vertices of the triangle. Pieces without area are dropped
+    pub fn clip(&self, rect: &Rect) -> Vec<Triangle2> {
+        let sides: [&dyn Fn(&Point2) -> f64; 4] = [
+            &|p| p.x - rect.min.x,
+            &|p| rect.max.x - p.x,
+            &|p| p.y - rect.min.y,
+            &|p| rect.max.y - p.y,
+        ];
+        let mut polygon = vec![self.p1, self.p2, self.p3];
+        for inside in sides.iter() {
+            polygon = clip_polygon(&polygon, inside);
+            if polygon.is_empty() {
+                return vec![];
+            }
+        }
+        // intersections of long edges may be a bit out of the rectangle after rounding
+        for p in polygon.iter_mut() {
+            p.x = p.x.max(rect.min.x).min(rect.max.x);
+            p.y = p.y.max(rect.min.y).min(rect.max.y);
+        }
+        (1..polygon.len().saturating_sub(1))
+            .map(|idx| Triangle2::new(polygon[0], polygon[idx], polygon[idx + 1]))
+            .filter(|t| Self::sign(&t.p1, &t.p2, &t.p3) != 0)
+            .collect()
+    }
+
+    /// Twice the area, positive if the vertices go counterclockwise with y going up
+    fn signed_area(&self) -> f64 {
+        (self.p2.x - self.p1.x) * (self.p3.y - self.p1.y)
+            - (self.p3.x - self.p1.x) * (self.p2.y - self.p1.y)
+    }
+
+    /// Convex polygon where the triangles overlap, the triangle is clipped by every edge
+    /// of the other one (Sutherland–Hodgman). Empty if the triangles don't overlap,
+    /// triangles touching each other give a polygon without area
+    pub fn overlap(&self, other: &Triangle2) -> Vec<Point2> {
+        // the inside of the other triangle is on the same side of all of its edges
+        let orientation = other.signed_area().signum();
+        let edges = [
+            (other.p1, other.p2),
+            (other.p2, other.p3),
+            (other.p3, other.p1),
+        ];
+        let mut polygon = vec![self.p1, self.p2, self.p3];
+        for (a, b) in edges.iter() {
+            let inside =
+                |p: &Point2| orientation * ((b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x));
+            polygon = clip_polygon(&polygon, &inside);
+            if polygon.is_empty() {
+                break;
+            }
+        }
+        polygon
+    }
+}
+
+/// Part of the convex polygon where `inside` is not negative, `inside` has to be linear
+fn clip_polygon(polygon: &[Point2], inside: &dyn Fn(&Point2) -> f64) -> Vec<Point2> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (idx, p) in polygon.iter().enumerate() {
+        let next = &polygon[(idx + 1) % polygon.len()];
+        let (d, d_next) = (inside(p), inside(next));
+        if d >= 0. {
+            clipped.push(*p);
+        }
+        if (d < 0.) != (d_next < 0.) {
+            let t = d / (d - d_next);
+            clipped.push(Point2::new(
+                p.x + (next.x - p.x) * t,
+                p.y + (next.y - p.y) * t,
+            ));
+        }
+    }
+    clipped
+}
+
+/// Area of the convex polygon
+pub fn polygon_area(polygon: &[Point2]) -> f64 {
+    (1..polygon.len().saturating_sub(1))
+        .map(|idx| Triangle2::new(polygon[0], polygon[idx], polygon[idx + 1]).signed_area())
+        .sum::<f64>()
+        .abs()
+        / 2.
 }
 
 #[cfg(test)]
@@ -173,71 +311,211 @@ mod test {
 
     #[test]
     fn basic_test() {
-        let x = Point2::new(1, 3);
+        let _x = Point2::new(1., 3.);
     }
 
     #[test]
     fn segments_intersection() {
-        let (p1, p2) = (Point2::new(-1, 0), &Point2::new(2, 0));
-        let seg1 = Segment::new(&p1, &p2);
-        let (p1, p2) = (Point2::new(0, -1), Point2::new(0, 2));
+        let (p1, p2) = (Point2::new(-1., 0.), &Point2::new(2., 0.));
+        let seg1 = Segment::new(&p1, p2);
+        let (p1, p2) = (Point2::new(0., -1.), Point2::new(0., 2.));
         let seg2 = Segment::new(&p1, &p2);
-        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0, 0)));
+        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0., 0.)));
 
-        let (p2, p1) = (Point2::new(1, 1), Point2::new(-1, -1));
+        let (p2, p1) = (Point2::new(1., 1.), Point2::new(-1., -1.));
         let seg1 = Segment::new(&p1, &p2);
-        let (p1, p2) = (Point2::new(1, -1), Point2::new(-2, 2));
+        let (p1, p2) = (Point2::new(1., -1.), Point2::new(-2., 2.));
         let seg2 = Segment::new(&p1, &p2);
-        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0, 0)));
+        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0., 0.)));
 
-        let (p1, p2) = (Point2::new(1, 1), Point2::new(-1, 1));
+        let (p1, p2) = (Point2::new(1., 1.), Point2::new(-1., 1.));
         let seg1 = Segment::new(&p1, &p2);
-        let (p1, p2) = (Point2::new(1, -1), Point2::new(-1, -2));
+        let (p1, p2) = (Point2::new(1., -1.), Point2::new(-1., -2.));
         let seg2 = Segment::new(&p1, &p2);
         assert_eq!(seg1.intersection(&seg2), None);
 
-        let (p1, p2) = (Point2::new(1, 0), Point2::new(0, 0));
+        let (p1, p2) = (Point2::new(1., 0.), Point2::new(0., 0.));
         let seg1 = Segment::new(&p1, &p2);
-        let (p1, p2) = (Point2::new(-1, 0), Point2::new(0, 0));
+        let (p1, p2) = (Point2::new(-1., 0.), Point2::new(0., 0.));
         let seg2 = Segment::new(&p1, &p2);
-        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0, 0)));
+        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(0., 0.)));
 
-        let (p1, p2) = (Point2::new(2, 0), Point2::new(0, 0));
+        let (p1, p2) = (Point2::new(2., 0.), Point2::new(0., 0.));
         let seg1 = Segment::new(&p1, &p2);
-        let (p1, p2) = (Point2::new(-1, 0), Point2::new(1, 0));
+        let (p1, p2) = (Point2::new(-1., 0.), Point2::new(1., 0.));
         let seg2 = Segment::new(&p1, &p2);
-        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(1, 0)));
+        assert_eq!(seg1.intersection(&seg2), Some(Point2::new(1., 0.)));
     }
 
     #[test]
     fn is_inside() {
-        let triangle = Triangle2::new(Point2::new(0, 0), Point2::new(2, 2), Point2::new(-2, 2));
-        assert!(triangle.is_inside(&Point2::new(0, 0)));
-        assert!(triangle.is_inside(&Point2::new(0, 1)));
-        assert!(!triangle.is_inside(&Point2::new(-1, -1)));
+        let triangle = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(2., 2.),
+            Point2::new(-2., 2.),
+        );
+        assert!(triangle.is_inside(&Point2::new(0., 0.)));
+        assert!(triangle.is_inside(&Point2::new(0., 1.)));
+        assert!(!triangle.is_inside(&Point2::new(-1., -1.)));
     }
 
     #[test]
     fn triangls_intersection_one_point() {
-        let triangle1 = Triangle2::new(Point2::new(0, 0), Point2::new(2, 2), Point2::new(-2, 2));
-        let triangle2 = Triangle2::new(Point2::new(0, 0), Point2::new(2, -2), Point2::new(-2, -2));
+        let triangle1 = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(2., 2.),
+            Point2::new(-2., 2.),
+        );
+        let triangle2 = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(2., -2.),
+            Point2::new(-2., -2.),
+        );
 
-        assert_eq!(triangle1.intersection(&triangle2), Some(Point2::new(0, 0)));
+        assert_eq!(
+            triangle1.intersection(&triangle2),
+            Some(Point2::new(0., 0.))
+        );
     }
 
     #[test]
     fn triangls_intersection_inside() {
-        let triangle1 = Triangle2::new(Point2::new(0, 0), Point2::new(10, 5), Point2::new(-10, 5));
-        let triangle2 = Triangle2::new(Point2::new(0, 1), Point2::new(1, 2), Point2::new(-1, 2));
+        let triangle1 = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(10., 5.),
+            Point2::new(-10., 5.),
+        );
+        let triangle2 = Triangle2::new(
+            Point2::new(0., 1.),
+            Point2::new(1., 2.),
+            Point2::new(-1., 2.),
+        );
 
-        assert_eq!(triangle1.intersection(&triangle2), Some(Point2::new(0, 1)));
+        assert_eq!(
+            triangle1.intersection(&triangle2),
+            Some(Point2::new(0., 1.))
+        );
     }
 
     #[test]
     fn triangls_intersection_dont_intersect() {
-        let triangle1 = Triangle2::new(Point2::new(1, 1), Point2::new(2, 2), Point2::new(-2, 2));
-        let triangle2 = Triangle2::new(Point2::new(0, 0), Point2::new(2, -2), Point2::new(-2, -2));
+        let triangle1 = Triangle2::new(
+            Point2::new(1., 1.),
+            Point2::new(2., 2.),
+            Point2::new(-2., 2.),
+        );
+        let triangle2 = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(2., -2.),
+            Point2::new(-2., -2.),
+        );
 
         assert_eq!(triangle1.intersection(&triangle2), None);
     }
+
+    fn area(t: &Triangle2) -> f64 {
+        ((t.p2.x - t.p1.x) * (t.p3.y - t.p1.y) - (t.p3.x - t.p1.x) * (t.p2.y - t.p1.y)).abs() / 2.
+    }
+
+    #[test]
+    fn clip_by_rect() {
+        let rect = Rect::new(Point2::new(0., 0.), Point2::new(1200., 800.));
+        let inside = Triangle2::new(
+            Point2::new(10., 10.),
+            Point2::new(20., 10.),
+            Point2::new(10., 30.),
+        );
+        assert_eq!(inside.clip(&rect), vec![inside.clone()]);
+
+        let outside = Triangle2::new(
+            Point2::new(-10., 10.),
+            Point2::new(-20., 10.),
+            Point2::new(-10., 30.),
+        );
+        assert!(outside.clip(&rect).is_empty());
+
+        // a corner of the triangle is cut off
+        let corner = Triangle2::new(
+            Point2::new(-10., 0.),
+            Point2::new(10., 0.),
+            Point2::new(-10., 20.),
+        );
+        let pieces = corner.clip(&rect);
+        let covered: f64 = pieces.iter().map(area).sum();
+        assert!((covered - 50.).abs() < 1e-9);
+
+        // huge triangle covering the whole rectangle
+        let huge = Triangle2::new(
+            Point2::new(-1e12, -1e12),
+            Point2::new(1e12, -1e12),
+            Point2::new(0., 1e12),
+        );
+        let pieces = huge.clip(&rect);
+        assert_eq!(pieces.len(), 2);
+        let covered: f64 = pieces.iter().map(area).sum();
+        // the cut points are rounded at the scale of the far vertices
+        assert!((covered - 1200. * 800.).abs() < 1.);
+        for t in pieces.iter() {
+            for p in [t.p1, t.p2, t.p3].iter() {
+                assert!(p.x >= 0. && p.x <= 1200. && p.y >= 0. && p.y <= 800.);
+            }
+            assert_eq!(
+                Triangle2::sign(&t.p1, &t.p2, &t.p3),
+                Triangle2::sign(&huge.p1, &huge.p2, &huge.p3)
+            );
+        }
+    }
+
+    #[test]
+    fn overlapping_rects() {
+        let rect = |x1, y1, x2, y2| Rect::new(Point2::new(x1, y1), Point2::new(x2, y2));
+        let rects = [
+            rect(0., 0., 2., 2.),
+            rect(5., 0., 6., 1.),
+            // touches the first one
+            rect(2., 2., 3., 3.),
+            // overlaps the first one along x only
+            rect(1., 5., 4., 6.),
+            rect(-1., -1., 10., 0.5),
+        ];
+        let mut pairs = overlapping_pairs(&rects);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 2), (0, 4), (1, 4)]);
+    }
+
+    #[test]
+    fn overlap_of_triangles() {
+        let triangle1 = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(4., 0.),
+            Point2::new(0., 4.),
+        );
+        // opposite order of vertices
+        let triangle2 = Triangle2::new(
+            Point2::new(1., 1.),
+            Point2::new(1., -3.),
+            Point2::new(5., 1.),
+        );
+        let overlap = triangle1.overlap(&triangle2);
+        assert!((polygon_area(&overlap) - 2.5).abs() < 1e-9);
+        assert!((polygon_area(&triangle2.overlap(&triangle1)) - 2.5).abs() < 1e-9);
+
+        let inside = Triangle2::new(
+            Point2::new(1., 1.),
+            Point2::new(2., 1.),
+            Point2::new(1., 2.),
+        );
+        assert_eq!(
+            triangle1.overlap(&inside),
+            vec![inside.p1, inside.p2, inside.p3]
+        );
+
+        // touching at a vertex
+        let touching = Triangle2::new(
+            Point2::new(0., 0.),
+            Point2::new(-1., -1.),
+            Point2::new(1., -1.),
+        );
+        assert_eq!(polygon_area(&triangle1.overlap(&touching)), 0.);
+    }
 }
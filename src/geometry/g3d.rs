@@ -1,63 +1,79 @@
+use std::collections::HashMap;
+
+use super::color::Color;
 use super::g2d;
-use crate::geometry::log;
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Point3 {
-    x: i32,
-    y: i32,
-    z: i32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
 }
 
 pub type Vector3 = Point3;
 
 impl Point3 {
-    pub fn new(x: i32, y: i32, z: i32) -> Self {
+    pub fn new(x: f64, y: f64, z: f64) -> Self {
         Point3 { x, y, z }
     }
 
+    /// Drops the depth of a point which is already in screen coordinates (see `Camera`)
     pub fn project(&self) -> g2d::Point2 {
         g2d::Point2::new(self.x, self.y)
     }
 
-    fn add(&self, other: &Point3) -> Point3 {
+    pub fn add(&self, other: &Point3) -> Point3 {
         Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
     }
 
-    fn neg(&self) -> Point3 {
+    pub fn neg(&self) -> Point3 {
         Point3::new(-self.x, -self.y, -self.z)
     }
 
-    pub fn shift(&self, scalar: i32) -> Point3 {
+    pub fn sub(&self, other: &Point3) -> Point3 {
+        self.add(&other.neg())
+    }
+
+    pub fn scale(&self, k: f64) -> Point3 {
+        Point3::new(self.x * k, self.y * k, self.z * k)
+    }
+
+    pub fn length(&self) -> f64 {
+        self.dot_product(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vector3 {
+        self.scale(1. / self.length())
+    }
+
+    /// Point on the segment from self to other: `t` = 0 gives self, `t` = 1 gives other
+    pub fn lerp(&self, other: &Point3, t: f64) -> Point3 {
+        self.add(&other.sub(self).scale(t))
+    }
+
+    pub fn shift(&self, scalar: f64) -> Point3 {
         Point3::new(self.x + scalar, self.y + scalar, self.z + scalar)
     }
 
     /// https://www.khanacademy.org/math/multivariable-calculus/thinking-about-multivariable-function/x786f2022:vectors-and-matrices/a/cross-products-mvc
-    fn x_product(&self, other: &Point3) -> Point3 {
+    pub fn x_product(&self, other: &Point3) -> Point3 {
         let x = self.y * other.z - self.z * other.y;
         let y = self.z * other.x - self.x * other.z;
         let z = self.x * other.y - self.y * other.x;
         Point3::new(x, y, z)
     }
 
-    fn dot_product(&self, other: &Point3) -> i32 {
+    pub fn dot_product(&self, other: &Point3) -> f64 {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
     /// https://stackoverflow.com/questions/6721544/circular-rotation-around-an-arbitrary-axis
-    fn rotate(&self, axis_point: &Point3, axis_vector: &Vector3, angle: f32) -> Point3 {
+    pub fn rotate(&self, axis_point: &Point3, axis_vector: &Vector3, angle: f64) -> Point3 {
         let sin = angle.sin();
         let cos = angle.cos();
-        let (x, y, z) = (self.x as f32, self.y as f32, self.z as f32);
-        let (a, b, c) = (
-            axis_point.x as f32,
-            axis_point.y as f32,
-            axis_point.z as f32,
-        );
-        let (u, v, w) = (
-            axis_vector.x as f32,
-            axis_vector.y as f32,
-            axis_vector.z as f32,
-        );
+        let (x, y, z) = (self.x, self.y, self.z);
+        let (a, b, c) = (axis_point.x, axis_point.y, axis_point.z);
+        let (u, v, w) = (axis_vector.x, axis_vector.y, axis_vector.z);
         let sm = (u.powf(2.) + v.powf(2.) + w.powf(2.)).sqrt();
         let (u, v, w) = (u / sm, v / sm, w / sm);
         let new_x = (a * (v * v + w * w) - u * (b * v + c * w - u * x - v * y - w * z))
@@ -72,24 +88,81 @@ impl Point3 {
             * (1. - cos)
             + z * cos
             + (-b * u + a * v - v * x + u * y) * sin;
-        Point3::new(
-            new_x.round() as i32,
-            new_y.round() as i32,
-            new_z.round() as i32,
-        )
+        Point3::new(new_x, new_y, new_z)
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Triagnle3 {
-    p1: Point3,
-    p2: Point3,
-    p3: Point3,
+    pub p1: Point3,
+    pub p2: Point3,
+    pub p3: Point3,
+    /// normals of the surface at p1, p2 and p3 in the world coordinates,
+    /// the normal of the triangle is used everywhere if they are not given
+    pub normals: Option<[Vector3; 3]>,
+    /// texture coordinates of p1, p2 and p3, see `Texture`
+    pub uvs: Option<[g2d::Point2; 3]>,
+    /// colors of p1, p2 and p3, they replace the fill color of the material
+    pub colors: Option<[Color; 3]>,
 }
 
 impl Triagnle3 {
     pub fn new(p1: Point3, p2: Point3, p3: Point3) -> Self {
-        Triagnle3 { p1, p2, p3 }
+        Triagnle3 {
+            p1,
+            p2,
+            p3,
+            normals: None,
+            uvs: None,
+            colors: None,
+        }
+    }
+
+    pub fn with_normals(mut self, normals: [Vector3; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [g2d::Point2; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn with_colors(mut self, colors: [Color; 3]) -> Self {
+        self.colors = Some(colors);
+        self
+    }
+
+    /// Average of the vertex colors
+    pub fn color(&self) -> Option<Color> {
+        self.colors
+            .map(|colors| Color::mix(&colors, [1. / 3., 1. / 3., 1. / 3.]))
+    }
+
+    /// Triangle with other vertices which keeps the vertex attributes of this one
+    pub fn with_points(&self, p1: Point3, p2: Point3, p3: Point3) -> Triagnle3 {
+        Triagnle3 {
+            p1,
+            p2,
+            p3,
+            ..self.clone()
+        }
+    }
+
+    /// Normals at p1, p2 and p3, not normalized
+    pub fn vertex_normals(&self) -> [Vector3; 3] {
+        self.normals.unwrap_or_else(|| {
+            let normal = self.normal();
+            [normal, normal, normal]
+        })
+    }
+
+    /// Not normalized normal of the triangle, its length is the doubled area.
+    /// Front side of the triangle is the side the normal points to
+    pub fn normal(&self) -> Vector3 {
+        let v1 = self.p3.sub(&self.p1);
+        let v2 = self.p2.sub(&self.p1);
+        v1.x_product(&v2)
     }
 
     /// if projection is a line it will return None
@@ -98,7 +171,7 @@ impl Triagnle3 {
         let p2 = self.p2.project();
         let p3 = self.p3.project();
 
-        let on_the_same_line = (p1.y - p2.y) * (p1.x - p3.x) == (p1.y - p3.y) * (p1.x - p2.x);
+        let on_the_same_line = (p1.y - p2.y) * (p1.x - p3.x) - (p1.y - p3.y) * (p1.x - p2.x) == 0.;
         if on_the_same_line {
             None
         } else {
@@ -106,63 +179,274 @@ impl Triagnle3 {
         }
     }
 
-    /// if true other should be painted BEFORE self
+    /// Order of the triangles in depth where their projections overlap.
+    /// Difference of heights of the planes of the triangles is linear,
+    /// so it is compared at the vertices of the overlap polygon, which covers the whole region
+    pub fn depth_order(&self, other: &Triagnle3) -> DepthOrder {
+        let (slf, otr) = match (self.project(), other.project()) {
+            (Some(slf), Some(otr)) => (slf, otr),
+            _ => return DepthOrder::Undetermined,
+        };
+        let overlap = slf.overlap(&otr);
+        if g2d::polygon_area(&overlap) < EPSILON {
+            return DepthOrder::Undetermined;
+        }
+        let (self_plane, other_plane) = (Plane::from_triangle(self), Plane::from_triangle(other));
+        let (mut above, mut below) = (false, false);
+        for p in overlap.iter() {
+            let heights = (self_plane.get_z(p.x, p.y), other_plane.get_z(p.x, p.y));
+            let (self_h, other_h) = match heights {
+                (Some(self_h), Some(other_h)) => (self_h, other_h),
+                _ => return DepthOrder::Undetermined,
+            };
+            // with the perspective projection heights are small, so the tolerance is relative
+            let tolerance = EPSILON * self_h.abs().max(other_h.abs()).max(1.);
+            above |= self_h - other_h > tolerance;
+            below |= other_h - self_h > tolerance;
+        }
+        match (above, below) {
+            (true, true) => DepthOrder::Crossing,
+            (true, false) => DepthOrder::Above,
+            (false, true) => DepthOrder::Below,
+            (false, false) => DepthOrder::Tie,
+        }
+    }
+
+    /// if true other should be painted BEFORE self,
+    /// triangles crossing each other are above each other
     pub fn is_above(&self, other: &Triagnle3) -> bool {
-        match (self.project(), other.project()) {
-            (Some(slf), Some(otr)) => {
-                if let Some(point) = slf.intersection(&otr) {
-                    let self_h = Plane::from_triangle(&self).get_z(point.x, point.y).unwrap();
-                    let other_h = Plane::from_triangle(&other)
-                        .get_z(point.x, point.y)
-                        .unwrap();
-                    log(&format!("self_h={};other_h={}", &self_h, &other_h));
-                    self_h > other_h
-                } else {
-                    false
-                }
-            }
-            _ => false,
+        match self.depth_order(other) {
+            DepthOrder::Above | DepthOrder::Crossing => true,
+            DepthOrder::Below | DepthOrder::Tie | DepthOrder::Undetermined => false,
         }
     }
 
-    pub fn rotate(&self, axis_point: &Point3, axis_vector: &Vector3, angle: f32) -> Triagnle3 {
+    pub fn rotate(&self, axis_point: &Point3, axis_vector: &Vector3, angle: f64) -> Triagnle3 {
         let p1 = self.p1.rotate(axis_point, axis_vector, angle);
         let p2 = self.p2.rotate(axis_point, axis_vector, angle);
         let p3 = self.p3.rotate(axis_point, axis_vector, angle);
-        Triagnle3::new(p1, p2, p3)
+        let origin = Point3::new(0., 0., 0.);
+        Triagnle3 {
+            normals: self
+                .normals
+                .map(|normals| normals.map(|n| n.rotate(&origin, axis_vector, angle))),
+            ..self.with_points(p1, p2, p3)
+        }
+    }
+
+    /// Cuts the triangle by the plane.
+    /// Returns pieces in front of the plane and pieces behind it,
+    /// triangle which lies on the plane is considered to be in front of it.
+    /// Vertex attributes of the pieces are interpolated
+    pub fn split(&self, plane: &Plane) -> (Vec<Triagnle3>, Vec<Triagnle3>) {
+        self.split_by(|p| plane.distance(p))
+    }
+
+    /// Same as `split` for the plane given by a function which is linear in coordinates
+    /// of points, positive values are in front of the plane
+    pub fn split_by<F: Fn(&Point3) -> f64>(&self, distance: F) -> (Vec<Triagnle3>, Vec<Triagnle3>) {
+        let points = [self.p1, self.p2, self.p3];
+        let corners = [
+            Corner::new(self.p1, [1., 0., 0.]),
+            Corner::new(self.p2, [0., 1., 0.]),
+            Corner::new(self.p3, [0., 0., 1.]),
+        ];
+        let distances: Vec<f64> = points
+            .iter()
+            .map(distance)
+            .map(|d| if d.abs() < EPSILON { 0. } else { d })
+            .collect();
+
+        if distances.iter().all(|&d| d >= 0.) {
+            return (vec![self.clone()], vec![]);
+        }
+        if distances.iter().all(|&d| d <= 0.) {
+            return (vec![], vec![self.clone()]);
+        }
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        for i in 0..3 {
+            let (a, b) = (&corners[i], &corners[(i + 1) % 3]);
+            let (da, db) = (distances[i], distances[(i + 1) % 3]);
+            if da >= 0. {
+                front.push(a.clone());
+            }
+            if da <= 0. {
+                back.push(a.clone());
+            }
+            if (da > 0. && db < 0.) || (da < 0. && db > 0.) {
+                let middle = a.lerp(b, da / (da - db));
+                front.push(middle.clone());
+                back.push(middle);
+            }
+        }
+        (self.fan(&front), self.fan(&back))
+    }
+
+    /// Triangulates convex polygon lying inside the triangle keeping the order of its vertices
+    fn fan(&self, corners: &[Corner]) -> Vec<Triagnle3> {
+        (1..corners.len().saturating_sub(1))
+            .map(|i| self.piece([&corners[0], &corners[i], &corners[i + 1]]))
+            .collect()
+    }
+
+    /// Triangle lying inside this one with interpolated vertex attributes
+    fn piece(&self, corners: [&Corner; 3]) -> Triagnle3 {
+        Triagnle3 {
+            p1: corners[0].point,
+            p2: corners[1].point,
+            p3: corners[2].point,
+            normals: self
+                .normals
+                .map(|normals| corners.map(|c| c.interpolate(&normals))),
+            uvs: self.uvs.map(|uvs| corners.map(|c| c.interpolate_uv(&uvs))),
+            colors: self
+                .colors
+                .map(|colors| corners.map(|c| Color::mix(&colors, c.weights))),
+        }
     }
 
     pub fn shift(&self, vector: &Vector3) -> Triagnle3 {
-        let p1 = self.p1.add(&vector);
-        let p2 = self.p2.add(&vector);
-        let p3 = self.p3.add(&vector);
-        Triagnle3::new(p1, p2, p3)
+        let p1 = self.p1.add(vector);
+        let p2 = self.p2.add(vector);
+        let p3 = self.p3.add(vector);
+        self.with_points(p1, p2, p3)
+    }
+}
+
+/// Point inside a triangle together with its barycentric coordinates
+#[derive(Clone)]
+struct Corner {
+    point: Point3,
+    weights: [f64; 3],
+}
+
+impl Corner {
+    fn new(point: Point3, weights: [f64; 3]) -> Self {
+        Corner { point, weights }
+    }
+
+    fn lerp(&self, other: &Corner, t: f64) -> Corner {
+        let w = |i: usize| self.weights[i] + (other.weights[i] - self.weights[i]) * t;
+        Corner::new(self.point.lerp(&other.point, t), [w(0), w(1), w(2)])
+    }
+
+    fn interpolate(&self, values: &[Vector3; 3]) -> Vector3 {
+        interpolate(values, self.weights)
+    }
+
+    fn interpolate_uv(&self, values: &[g2d::Point2; 3]) -> g2d::Point2 {
+        interpolate_uv(values, self.weights)
     }
 }
 
-struct Plane {
-    a: i32,
-    b: i32,
-    c: i32,
-    d: i32,
+/// Weighted sum of the vectors given at the vertices of a triangle,
+/// `weights` are barycentric coordinates of a point of the triangle
+pub fn interpolate(values: &[Vector3; 3], weights: [f64; 3]) -> Vector3 {
+    values[0]
+        .scale(weights[0])
+        .add(&values[1].scale(weights[1]))
+        .add(&values[2].scale(weights[2]))
+}
+
+/// Same as `interpolate` for texture coordinates
+pub fn interpolate_uv(values: &[g2d::Point2; 3], weights: [f64; 3]) -> g2d::Point2 {
+    g2d::Point2::new(
+        values[0].x * weights[0] + values[1].x * weights[1] + values[2].x * weights[2],
+        values[0].y * weights[0] + values[1].y * weights[1] + values[2].y * weights[2],
+    )
+}
+
+/// Copies of the triangles where triangles without vertex normals get normals
+/// averaged over all triangles sharing the vertex, so the surface looks smooth when it is shaded.
+/// Normals of bigger triangles have bigger weight, normals given by models are kept
+pub fn smooth_normals(triangles: &[Triagnle3]) -> Vec<Triagnle3> {
+    let key = |p: &Point3| (p.x.to_bits(), p.y.to_bits(), p.z.to_bits());
+    let mut sums: HashMap<(u64, u64, u64), Vector3> = HashMap::new();
+    for t in triangles.iter() {
+        let normal = t.normal();
+        for p in [t.p1, t.p2, t.p3].iter() {
+            let sum = sums
+                .entry(key(p))
+                .or_insert_with(|| Point3::new(0., 0., 0.));
+            *sum = sum.add(&normal);
+        }
+    }
+    triangles
+        .iter()
+        .map(|t| {
+            if t.normals.is_some() {
+                return t.clone();
+            }
+            let normal = |p: &Point3| sums[&key(p)];
+            t.clone()
+                .with_normals([normal(&t.p1), normal(&t.p2), normal(&t.p3)])
+        })
+        .collect()
+}
+
+/// Points closer than this to a plane are considered to be on the plane
+const EPSILON: f64 = 1e-9;
+
+/// Result of `Triagnle3::depth_order`, the bigger screen z is the closer a point is to the viewer
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DepthOrder {
+    /// The triangle is closer to the viewer over the whole overlap
+    Above,
+    /// The triangle is further from the viewer over the whole overlap
+    Below,
+    /// The triangles cross each other, each one is closer somewhere in the overlap
+    Crossing,
+    /// The triangles are at the same depth over the whole overlap
+    Tie,
+    /// Projections don't overlap or overlap without area, so the order doesn't matter
+    Undetermined,
+}
+
+/// a * x + b * y + c * z = d
+pub struct Plane {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
 }
 
 impl Plane {
-    fn new(a: i32, b: i32, c: i32, d: i32) -> Self {
+    fn new(a: f64, b: f64, c: f64, d: f64) -> Self {
         Plane { a, b, c, d }
     }
 
     /// https://kitchingroup.cheme.cmu.edu/blog/2015/01/18/Equation-of-a-plane-through-three-points/
-    fn from_triangle(triangle: &Triagnle3) -> Self {
-        let v1 = triangle.p3.add(&triangle.p1.neg());
-        let v2 = triangle.p2.add(&triangle.p1.neg());
-        let x_prod = v1.x_product(&v2);
-        let dot_prod = x_prod.dot_product(&triangle.p3);
-        Plane::new(x_prod.x, x_prod.y, x_prod.z, dot_prod)
+    pub fn from_triangle(triangle: &Triagnle3) -> Self {
+        let normal = triangle.normal();
+        let dot_prod = normal.dot_product(&triangle.p3);
+        Plane::new(normal.x, normal.y, normal.z, dot_prod)
+    }
+
+    pub fn normal(&self) -> Vector3 {
+        Vector3::new(self.a, self.b, self.c)
+    }
+
+    /// Planes of degenerate triangles have zero normal
+    pub fn is_degenerate(&self) -> bool {
+        self.normal().length() == 0.
     }
 
-    fn get_z(&self, x: i32, y: i32) -> Option<i32> {
-        if self.c == 0 {
+    pub fn contains(&self, triangle: &Triagnle3) -> bool {
+        [triangle.p1, triangle.p2, triangle.p3]
+            .iter()
+            .all(|p| self.distance(p).abs() < EPSILON)
+    }
+
+    /// Signed distance from the plane to the point
+    pub fn distance(&self, p: &Point3) -> f64 {
+        let norm = (self.a * self.a + self.b * self.b + self.c * self.c).sqrt();
+        (self.a * p.x + self.b * p.y + self.c * p.z - self.d) / norm
+    }
+
+    fn get_z(&self, x: f64, y: f64) -> Option<f64> {
+        if self.c == 0. {
             return None;
         }
         Some((self.d - x * self.a - y * self.b) / self.c)
@@ -176,26 +460,26 @@ mod test {
     #[test]
     fn plane() {
         let triangle = Triagnle3::new(
-            Point3::new(0, 0, 0),
-            Point3::new(-1, 1, 0),
-            Point3::new(1, 1, 0),
+            Point3::new(0., 0., 0.),
+            Point3::new(-1., 1., 0.),
+            Point3::new(1., 1., 0.),
         );
         let plane = Plane::from_triangle(&triangle);
 
-        assert_eq!(plane.get_z(0, 0), Some(0));
+        assert_eq!(plane.get_z(0., 0.), Some(0.));
     }
 
     #[test]
     fn parallel_triangles() {
         let triangle1 = Triagnle3::new(
-            Point3::new(0, 0, 0),
-            Point3::new(-1, 1, 0),
-            Point3::new(1, 1, 0),
+            Point3::new(0., 0., 0.),
+            Point3::new(-1., 1., 0.),
+            Point3::new(1., 1., 0.),
         );
         let triangle2 = Triagnle3::new(
-            Point3::new(0, 0, 1),
-            Point3::new(-1, 1, 1),
-            Point3::new(1, 1, 1),
+            Point3::new(0., 0., 1.),
+            Point3::new(-1., 1., 1.),
+            Point3::new(1., 1., 1.),
         );
 
         assert!(!triangle1.is_above(&triangle2));
@@ -204,67 +488,207 @@ mod test {
 
     #[test]
     fn overlapping_triangles() {
+        // the projections overlap and the triangles share a vertex where they have the same height
         let triangle1 = Triagnle3::new(
-            Point3::new(0, 0, 0),
-            Point3::new(-1, 1, -1),
-            Point3::new(1, 1, -1),
+            Point3::new(0., 0., 0.),
+            Point3::new(-1., 1., -1.),
+            Point3::new(1., 1., -1.),
         );
         let triangle2 = Triagnle3::new(
-            Point3::new(0, 0, 1),
-            Point3::new(-1, -1, 2),
-            Point3::new(1, -1, 2),
+            Point3::new(0., 0., 0.),
+            Point3::new(-1., 2., 1.),
+            Point3::new(1., 2., 1.),
         );
 
+        assert_eq!(triangle1.depth_order(&triangle2), DepthOrder::Below);
         assert!(!triangle1.is_above(&triangle2));
         assert!(triangle2.is_above(&triangle1));
     }
 
+    #[test]
+    fn touching_triangles() {
+        let triangle1 = Triagnle3::new(
+            Point3::new(0., 0., 0.),
+            Point3::new(-1., 1., -1.),
+            Point3::new(1., 1., -1.),
+        );
+        let triangle2 = Triagnle3::new(
+            Point3::new(0., 0., 1.),
+            Point3::new(-1., -1., 2.),
+            Point3::new(1., -1., 2.),
+        );
+
+        assert_eq!(triangle1.depth_order(&triangle2), DepthOrder::Undetermined);
+        assert!(!triangle1.is_above(&triangle2));
+        assert!(!triangle2.is_above(&triangle1));
+    }
+
+    #[test]
+    fn tie_and_crossing() {
+        let triangle1 = Triagnle3::new(
+            Point3::new(0., 0., 0.),
+            Point3::new(4., 0., 0.),
+            Point3::new(0., 4., 0.),
+        );
+        let coplanar = triangle1.shift(&Vector3::new(1., 1., 0.));
+        assert_eq!(triangle1.depth_order(&coplanar), DepthOrder::Tie);
+        assert!(!triangle1.is_above(&coplanar) && !coplanar.is_above(&triangle1));
+
+        let crossing = Triagnle3::new(
+            Point3::new(1., 1., -1.),
+            Point3::new(3., 1., 1.),
+            Point3::new(1., 3., -1.),
+        );
+        assert_eq!(triangle1.depth_order(&crossing), DepthOrder::Crossing);
+        assert!(triangle1.is_above(&crossing) && crossing.is_above(&triangle1));
+    }
+
     #[test]
     fn non_overlapping_triangles() {
         let triangle1 = Triagnle3::new(
-            Point3::new(0, 1, 0),
-            Point3::new(-1, 2, -1),
-            Point3::new(1, 2, -1),
+            Point3::new(0., 1., 0.),
+            Point3::new(-1., 2., -1.),
+            Point3::new(1., 2., -1.),
         );
         let triangle2 = Triagnle3::new(
-            Point3::new(0, 0, 1),
-            Point3::new(-1, -1, 2),
-            Point3::new(1, -1, 2),
+            Point3::new(0., 0., 1.),
+            Point3::new(-1., -1., 2.),
+            Point3::new(1., -1., 2.),
         );
 
         assert!(!triangle1.is_above(&triangle2));
         assert!(!triangle2.is_above(&triangle1));
     }
 
+    #[test]
+    fn split_triangle() {
+        let triangle = Triagnle3::new(
+            Point3::new(-1., 0., 0.),
+            Point3::new(1., 0., 0.),
+            Point3::new(0., 2., 0.),
+        );
+        let horizontal = Triagnle3::new(
+            Point3::new(0., 1., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(0., 1., 1.),
+        );
+        let (front, back) = triangle.split(&Plane::from_triangle(&horizontal));
+        let (top, bottom) = if front.len() == 1 {
+            (front, back)
+        } else {
+            (back, front)
+        };
+        assert_eq!(top.len(), 1);
+        assert_eq!(bottom.len(), 2);
+        assert!(top[0].p1.y >= 1. && top[0].p2.y >= 1. && top[0].p3.y >= 1.);
+        for t in bottom.iter() {
+            assert!(t.p1.y <= 1. && t.p2.y <= 1. && t.p3.y <= 1.);
+        }
+
+        // plane which doesn't cross the triangle
+        let (front, back) = horizontal.split(&Plane::from_triangle(&triangle));
+        assert_eq!(front.len() + back.len(), 1);
+        let (front, back) = triangle.split(&Plane::from_triangle(&triangle));
+        assert_eq!((front, back), (vec![triangle], vec![]));
+    }
+
+    #[test]
+    fn split_interpolates_normals() {
+        let triangle = Triagnle3::new(
+            Point3::new(0., 0., 0.),
+            Point3::new(2., 0., 0.),
+            Point3::new(0., 2., 0.),
+        )
+        .with_normals([
+            Vector3::new(1., 0., 0.),
+            Vector3::new(0., 1., 0.),
+            Vector3::new(0., 0., 1.),
+        ]);
+        let vertical = Triagnle3::new(
+            Point3::new(1., 0., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(1., 0., 1.),
+        );
+        let (front, back) = triangle.split(&Plane::from_triangle(&vertical));
+        for piece in front.iter().chain(back.iter()) {
+            let points = [piece.p1, piece.p2, piece.p3];
+            let normals = piece.normals.unwrap();
+            for (p, n) in points.iter().zip(normals.iter()) {
+                // barycentric coordinates of the vertex are the normal
+                let expected = Vector3::new(1. - p.x / 2. - p.y / 2., p.x / 2., p.y / 2.);
+                assert_close(*n, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals_are_averaged() {
+        // two halves of a roof sharing the ridge from (0, 0, 1) to (0, 1, 1)
+        let left = Triagnle3::new(
+            Point3::new(-1., 0., 0.),
+            Point3::new(0., 0., 1.),
+            Point3::new(0., 1., 1.),
+        );
+        let right = Triagnle3::new(
+            Point3::new(0., 0., 1.),
+            Point3::new(1., 0., 0.),
+            Point3::new(0., 1., 1.),
+        );
+        let smooth = smooth_normals(&[left.clone(), right.clone()]);
+        let ridge = left.normal().add(&right.normal());
+        assert_close(ridge.normalize(), Vector3::new(0., 0., -1.));
+        assert_eq!(smooth[0].normals, Some([left.normal(), ridge, ridge]));
+        assert_eq!(smooth[1].normals, Some([ridge, right.normal(), ridge]));
+
+        let up = Vector3::new(0., 0., 1.);
+        let given = right.clone().with_normals([up, up, up]);
+        let smooth = smooth_normals(&[left.clone(), given.clone()]);
+        assert_eq!(smooth[0].normals, Some([left.normal(), ridge, ridge]));
+        assert_eq!(smooth[1], given);
+    }
+
+    fn assert_close(left: Point3, right: Point3) {
+        assert!(
+            left.sub(&right).length() < 1e-9,
+            "{:?} != {:?}",
+            left,
+            right
+        );
+    }
+
     #[test]
     fn point_rotation() {
-        assert_eq!(
-            Point3::new(1, 1, 1).rotate(&Point3::new(0, 0, 0), &Vector3::new(1, 1, 1), 10.),
-            Point3::new(1, 1, 1)
+        assert_close(
+            Point3::new(1., 1., 1.).rotate(
+                &Point3::new(0., 0., 0.),
+                &Vector3::new(1., 1., 1.),
+                10.,
+            ),
+            Point3::new(1., 1., 1.),
         );
-        assert_eq!(
-            Point3::new(1, 0, 0).rotate(
-                &Point3::new(0, 0, 0),
-                &Vector3::new(0, 0, 1),
-                std::f32::consts::PI / 2.
+        assert_close(
+            Point3::new(1., 0., 0.).rotate(
+                &Point3::new(0., 0., 0.),
+                &Vector3::new(0., 0., 1.),
+                std::f64::consts::PI / 2.,
             ),
-            Point3::new(0, 1, 0)
+            Point3::new(0., 1., 0.),
         );
-        assert_eq!(
-            Point3::new(0, 1, 0).rotate(
-                &Point3::new(0, 0, 0),
-                &Vector3::new(0, 0, 1),
-                -std::f32::consts::PI / 2.
+        assert_close(
+            Point3::new(0., 1., 0.).rotate(
+                &Point3::new(0., 0., 0.),
+                &Vector3::new(0., 0., 1.),
+                -std::f64::consts::PI / 2.,
             ),
-            Point3::new(1, 0, 0)
+            Point3::new(1., 0., 0.),
         );
-        assert_eq!(
-            Point3::new(1, 1, 0).rotate(
-                &Point3::new(0, 0, 0),
-                &Vector3::new(-1, 1, 0),
-                std::f32::consts::PI / 2.
+        assert_close(
+            Point3::new(1., 1., 0.).rotate(
+                &Point3::new(0., 0., 0.),
+                &Vector3::new(-1., 1., 0.),
+                std::f64::consts::PI / 2.,
             ),
-            Point3::new(0, 0, -1)
+            Point3::new(0., 0., -std::f64::consts::SQRT_2),
         );
     }
 }
@@ -0,0 +1,162 @@
+use super::color::Color;
+use super::g3d::Vector3;
+
+/// Light which reaches every triangle from every direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmbientLight {
+    pub color: Color,
+    pub intensity: f64,
+}
+
+impl AmbientLight {
+    pub fn new(color: Color, intensity: f64) -> Self {
+        AmbientLight { color, intensity }
+    }
+}
+
+/// Light coming from infinitely far away, `direction` is where the light goes
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vector3,
+    pub color: Color,
+    pub intensity: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3, color: Color, intensity: f64) -> Self {
+        DirectionalLight {
+            direction,
+            color,
+            intensity,
+        }
+    }
+}
+
+/// Lights of the scene, colors of surfaces are computed by the Lambert's cosine law
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lighting {
+    pub ambient: AmbientLight,
+    pub directional: Vec<DirectionalLight>,
+}
+
+impl Lighting {
+    pub fn new(ambient: AmbientLight, directional: Vec<DirectionalLight>) -> Self {
+        Lighting {
+            ambient,
+            directional,
+        }
+    }
+
+    /// Surfaces keep their own color
+    pub fn unlit() -> Self {
+        Lighting::new(AmbientLight::new(Color::rgb(255, 255, 255), 1.), Vec::new())
+    }
+
+    /// Color of the surface with the color `base` whose visible side looks along `normal`,
+    /// the normal doesn't have to be normalized. Alpha of the base color is kept
+    pub fn shade(&self, base: Color, normal: &Vector3) -> Color {
+        illuminate(base, self.light(normal))
+    }
+
+    /// Light reaching the surface whose visible side looks along `normal`,
+    /// given as a multiplier of every channel of the color of the surface
+    pub fn light(&self, normal: &Vector3) -> [f64; 3] {
+        let mut light = channels(self.ambient.color, self.ambient.intensity);
+        let length = normal.length();
+        if length > 0. {
+            let normal = normal.scale(1. / length);
+            for l in self.directional.iter() {
+                let direction = l.direction.normalize();
+                let cos = -normal.dot_product(&direction);
+                if cos > 0. {
+                    let c = channels(l.color, l.intensity * cos);
+                    for i in 0..3 {
+                        light[i] += c[i];
+                    }
+                }
+            }
+        }
+        light
+    }
+}
+
+/// Color of the surface with the color `base` lit by `light` (see `Lighting::light`)
+pub fn illuminate(base: Color, light: [f64; 3]) -> Color {
+    let apply = |channel: u8, light: f64| (channel as f64 * light).round().min(255.) as u8;
+    Color::new(
+        apply(base.r, light[0]),
+        apply(base.g, light[1]),
+        apply(base.b, light[2]),
+        base.a,
+    )
+}
+
+impl Default for Lighting {
+    /// Dim ambient light and a light shining from the top left corner of the default view
+    fn default() -> Self {
+        Lighting::new(
+            AmbientLight::new(Color::rgb(255, 255, 255), 0.3),
+            vec![DirectionalLight::new(
+                Vector3::new(1., 1., -2.),
+                Color::rgb(255, 255, 255),
+                0.7,
+            )],
+        )
+    }
+}
+
+/// Intensity of every channel of the light in 0..1 per unit of `intensity`
+fn channels(color: Color, intensity: f64) -> [f64; 3] {
+    [
+        color.r as f64 / 255. * intensity,
+        color.g as f64 / 255. * intensity,
+        color.b as f64 / 255. * intensity,
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lambert_shading() {
+        let white = Color::rgb(255, 255, 255);
+        let lighting = Lighting::new(
+            AmbientLight::new(white, 0.2),
+            vec![DirectionalLight::new(Vector3::new(0., 0., -1.), white, 0.6)],
+        );
+        let base = Color::new(100, 200, 0, 128);
+        assert_eq!(
+            lighting.shade(base, &Vector3::new(0., 0., 5.)),
+            Color::new(80, 160, 0, 128)
+        );
+        // the light comes at 60 degrees
+        let tilted = Vector3::new(3_f64.sqrt(), 0., 1.);
+        assert_eq!(lighting.shade(base, &tilted), Color::new(50, 100, 0, 128));
+        // the surface looks away from the light
+        assert_eq!(
+            lighting.shade(base, &Vector3::new(0., 0., -1.)),
+            Color::new(20, 40, 0, 128)
+        );
+        assert_eq!(
+            Lighting::unlit().shade(base, &Vector3::new(0., 0., -1.)),
+            base
+        );
+    }
+
+    #[test]
+    fn colored_lights_are_added() {
+        let lighting = Lighting::new(
+            AmbientLight::new(Color::rgb(255, 0, 0), 1.),
+            vec![DirectionalLight::new(
+                Vector3::new(0., 0., -1.),
+                Color::rgb(0, 0, 255),
+                2.,
+            )],
+        );
+        assert_eq!(
+            lighting.shade(Color::rgb(200, 200, 200), &Vector3::new(0., 0., 1.)),
+            Color::rgb(200, 0, 255)
+        );
+    }
+}
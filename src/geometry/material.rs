@@ -0,0 +1,74 @@
+use std::rc::Rc;
+
+use super::color::Color;
+use super::texture::Texture;
+
+/// How triangles of a shape are painted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub fill: Color,
+    pub stroke: Color,
+    /// 0 is fully transparent, 1 keeps alpha of the fill and stroke colors
+    pub opacity: f64,
+    /// width of edges in pixels
+    pub stroke_width: f64,
+    pub edges_visible: bool,
+    /// replaces the fill color in the rasterized frame for triangles with texture coordinates
+    pub texture: Option<Rc<Texture>>,
+}
+
+impl Material {
+    pub fn new(fill: Color, stroke: Color) -> Self {
+        Material {
+            fill,
+            stroke,
+            opacity: 1.,
+            stroke_width: 1.,
+            edges_visible: true,
+            texture: None,
+        }
+    }
+
+    /// Fill color with the opacity applied to its alpha
+    pub fn fill_color(&self) -> Color {
+        self.apply_opacity(self.fill)
+    }
+
+    /// Stroke color with the opacity applied to its alpha,
+    /// transparent if edges are not visible
+    pub fn stroke_color(&self) -> Color {
+        if self.edges_visible {
+            self.apply_opacity(self.stroke)
+        } else {
+            Color::transparent()
+        }
+    }
+
+    /// The color with the opacity applied to its alpha
+    pub fn apply_opacity(&self, color: Color) -> Color {
+        let alpha = (color.a as f64 * self.opacity.clamp(0., 1.)).round() as u8;
+        Color::new(color.r, color.g, color.b, alpha)
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::new(Color::rgb(0x00, 0xF0, 0x0F), Color::rgb(0xF0, 0x00, 0x0F))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn opacity_and_edges() {
+        let mut material = Material::new(Color::rgb(1, 2, 3), Color::new(4, 5, 6, 100));
+        assert_eq!(material.fill_color(), Color::rgb(1, 2, 3));
+        material.opacity = 0.5;
+        assert_eq!(material.fill_color(), Color::new(1, 2, 3, 128));
+        assert_eq!(material.stroke_color(), Color::new(4, 5, 6, 50));
+        material.edges_visible = false;
+        assert_eq!(material.stroke_color(), Color::transparent());
+    }
+}
@@ -0,0 +1,116 @@
+use super::color::Color;
+use super::g2d::Point2;
+use super::g3d::{Point3, Triagnle3, Vector3};
+use super::shape::Shape;
+
+/// Triangles sharing their vertices, attributes of vertices are optional
+/// and when present there is one value per position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vector3>>,
+    pub uvs: Option<Vec<Point2>>,
+    pub colors: Option<Vec<Color>>,
+    /// Indices of positions of every triangle,
+    /// in the order of points of `Triagnle3` which defines its normal
+    pub indices: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Returns None if some index is out of the positions
+    pub fn new(positions: Vec<Point3>, indices: Vec<[usize; 3]>) -> Option<Self> {
+        if indices.iter().flatten().any(|&i| i >= positions.len()) {
+            return None;
+        }
+        Some(Mesh {
+            positions,
+            normals: None,
+            uvs: None,
+            colors: None,
+            indices,
+        })
+    }
+
+    /// Returns None if the number of normals doesn't match the number of positions,
+    /// same for other attributes
+    pub fn with_normals(mut self, normals: Vec<Vector3>) -> Option<Self> {
+        if normals.len() != self.positions.len() {
+            return None;
+        }
+        self.normals = Some(normals);
+        Some(self)
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<Point2>) -> Option<Self> {
+        if uvs.len() != self.positions.len() {
+            return None;
+        }
+        self.uvs = Some(uvs);
+        Some(self)
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Color>) -> Option<Self> {
+        if colors.len() != self.positions.len() {
+            return None;
+        }
+        self.colors = Some(colors);
+        Some(self)
+    }
+
+    fn triangle(&self, [a, b, c]: [usize; 3]) -> Triagnle3 {
+        let mut triangle = Triagnle3::new(self.positions[a], self.positions[b], self.positions[c]);
+        if let Some(normals) = &self.normals {
+            triangle = triangle.with_normals([normals[a], normals[b], normals[c]]);
+        }
+        if let Some(uvs) = &self.uvs {
+            triangle = triangle.with_uvs([uvs[a], uvs[b], uvs[c]]);
+        }
+        if let Some(colors) = &self.colors {
+            triangle = triangle.with_colors([colors[a], colors[b], colors[c]]);
+        }
+        triangle
+    }
+}
+
+impl Shape for Mesh {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.indices.iter().map(|&idx| self.triangle(idx)).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shared_vertices() {
+        let positions = vec![
+            Point3::new(0., 0., 0.),
+            Point3::new(1., 0., 0.),
+            Point3::new(1., 1., 0.),
+            Point3::new(0., 1., 0.),
+        ];
+        assert!(Mesh::new(positions.clone(), vec![[0, 1, 4]]).is_none());
+
+        let colors = vec![
+            Color::rgb(255, 0, 0),
+            Color::rgb(0, 255, 0),
+            Color::rgb(0, 0, 255),
+            Color::rgb(0, 0, 0),
+        ];
+        let mesh = Mesh::new(positions.clone(), vec![[0, 1, 2], [0, 2, 3]])
+            .unwrap()
+            .with_colors(colors.clone())
+            .unwrap();
+        assert!(mesh.clone().with_normals(vec![]).is_none());
+
+        let triangles = mesh.approximate();
+        assert_eq!(triangles.len(), 2);
+        assert_eq!(
+            triangles[1],
+            Triagnle3::new(positions[0], positions[2], positions[3])
+                .with_colors([colors[0], colors[2], colors[3]])
+        );
+        assert_eq!(triangles[0].normals, None);
+    }
+}
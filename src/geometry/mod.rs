@@ -1,196 +1,1223 @@
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::HashMap;
 
-use self::g3d::{Point3, Vector3};
+use self::bsp::BspTree;
+use self::camera::{Camera, Projection};
+use self::color::Color;
+use self::g3d::Point3;
+use self::light::Lighting;
+use self::material::Material;
+use self::raster::FrameBuffer;
 
+mod bsp;
+pub mod camera;
+pub mod color;
 pub mod g2d;
 pub mod g3d;
+pub mod light;
+pub mod material;
+mod mesh;
+pub mod raster;
+mod shape;
+mod svg;
+pub mod texture;
+
+pub use self::mesh::Mesh;
+pub use self::shape::{Animated, Animation, SetOfTriangles, Shape, Transform};
 use wasm_bindgen::prelude::*;
 
+pub type ShapeId = u32;
+
+/// Number of values describing one triangle in the buffer filled by `SceneTmp::render`
+pub const RENDER_STRIDE: usize = 15;
+
+/// Limits the number of times the cycles in the ordering graph are split
+const MAX_SPLIT_ROUNDS: usize = 8;
+
+/// How triangles are sorted for painting
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OrderingMode {
+    /// Topological order of the graph built by `Triagnle3::is_above` every frame
+    Graph,
+    /// Traversal of the BSP tree which is rebuilt only when triangles of the scene change,
+    /// good for static geometry and a moving camera
+    Bsp,
+}
+
+/// Which side of triangles is not drawn.
+/// Front side of a triangle is the one its normal points to (see `Triagnle3::normal`)
 #[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = console)]
-    pub fn log(s: &str);
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
 }
 
-pub trait Shape {
-    fn approximate(&self) -> Vec<g3d::Triagnle3>;
+/// How the color changes across a triangle in the rasterized frame,
+/// the painted frame always uses the flat shading
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ShadingMode {
+    /// One color computed from the normal of the triangle
+    Flat,
+    /// Colors computed at the vertices from the vertex normals are interpolated
+    Gouraud,
+    /// Vertex normals are interpolated and the color is computed for every pixel
+    Phong,
 }
 
-pub struct SetOfTriangles {
-    triangles: Vec<g3d::Triagnle3>,
-    axis_point: Point3,
-    axis_vector: Vector3,
-    angle: f32,
-    intersept: Vector3,
-    move_speed: i32,
-    rotation_speed: f32,
+struct SceneObject {
+    id: ShapeId,
+    shape: Box<dyn Shape>,
+    cull_mode: CullMode,
+    material: Material,
 }
 
-impl SetOfTriangles {
-    pub fn new(
-        triangles: Vec<g3d::Triagnle3>,
-        axis_point: Point3,
-        axis_vector: Vector3,
-        angle: f32,
-        intersept: Vector3,
-    ) -> Self {
-        SetOfTriangles {
-            triangles,
-            axis_point,
-            axis_vector,
-            angle,
-            intersept,
-            move_speed: 0,
-            rotation_speed: 0.01,
-        }
-    }
-    pub fn step(&mut self) {
-        self.angle += self.rotation_speed;
-        self.intersept = self.intersept.shift(self.move_speed);
-    }
+/// Triangle of the scene together with the index of the object it belongs to
+/// and its color, pieces of a split triangle belong to the same object and have the same color
+#[derive(Debug, Clone, PartialEq)]
+struct Face {
+    object: usize,
+    triangle: g3d::Triagnle3,
+    color: Color,
 }
 
-impl Shape for SetOfTriangles {
-    fn approximate(&self) -> Vec<g3d::Triagnle3> {
-        self.triangles
-            .iter()
-            .map(|t| t.rotate(&self.axis_point, &self.axis_vector, self.angle))
-            .map(|t| t.shift(&self.intersept))
-            .collect()
+impl Face {
+    /// Pieces in front of the plane and pieces behind it, see `Triagnle3::split`
+    fn split(&self, plane: &g3d::Plane) -> (Vec<Face>, Vec<Face>) {
+        let (front, back) = self.triangle.split(plane);
+        let wrap = |triangles: Vec<g3d::Triagnle3>| {
+            triangles
+                .into_iter()
+                .map(|triangle| Face {
+                    object: self.object,
+                    triangle,
+                    color: self.color,
+                })
+                .collect()
+        };
+        (wrap(front), wrap(back))
     }
 }
 
 pub struct SceneTmp {
-    shapes: SetOfTriangles,
+    objects: Vec<SceneObject>,
+    next_id: ShapeId,
+    camera: Camera,
+    ordering_mode: OrderingMode,
+    /// BSP tree and the faces it was built from
+    bsp: RefCell<Option<(Vec<Face>, BspTree)>>,
+    lighting: Lighting,
+    shading_mode: ShadingMode,
 }
 
 impl SceneTmp {
-    pub fn new(shapes: SetOfTriangles) -> Self {
-        Self { shapes }
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            objects: Vec::new(),
+            next_id: 0,
+            camera,
+            ordering_mode: OrderingMode::Graph,
+            bsp: RefCell::new(None),
+            lighting: Lighting::default(),
+            shading_mode: ShadingMode::Flat,
+        }
+    }
+
+    pub fn ordering_mode(&self) -> OrderingMode {
+        self.ordering_mode
+    }
+
+    pub fn set_ordering_mode(&mut self, mode: OrderingMode) {
+        self.ordering_mode = mode;
+    }
+
+    pub fn shading_mode(&self) -> ShadingMode {
+        self.shading_mode
+    }
+
+    pub fn set_shading_mode(&mut self, mode: ShadingMode) {
+        self.shading_mode = mode;
+    }
+
+    pub fn add_shape(&mut self, shape: Box<dyn Shape>) -> ShapeId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.objects.push(SceneObject {
+            id,
+            shape,
+            cull_mode: CullMode::None,
+            material: Material::default(),
+        });
+        id
+    }
+
+    /// Returns removed shape or None if there is no shape with such id
+    pub fn remove_shape(&mut self, id: ShapeId) -> Option<Box<dyn Shape>> {
+        let idx = self.objects.iter().position(|o| o.id == id)?;
+        Some(self.objects.remove(idx).shape)
+    }
+
+    pub fn shape(&self, id: ShapeId) -> Option<&dyn Shape> {
+        self.object(id).map(|o| o.shape.as_ref())
+    }
+
+    pub fn shape_mut(&mut self, id: ShapeId) -> Option<&mut Box<dyn Shape>> {
+        self.object_mut(id).map(|o| &mut o.shape)
+    }
+
+    pub fn material(&self, id: ShapeId) -> Option<&Material> {
+        self.object(id).map(|o| &o.material)
+    }
+
+    pub fn material_mut(&mut self, id: ShapeId) -> Option<&mut Material> {
+        self.object_mut(id).map(|o| &mut o.material)
+    }
+
+    pub fn shape_ids(&self) -> Vec<ShapeId> {
+        self.objects.iter().map(|o| o.id).collect()
+    }
+
+    fn object(&self, id: ShapeId) -> Option<&SceneObject> {
+        self.objects.iter().find(|o| o.id == id)
+    }
+
+    fn object_mut(&mut self, id: ShapeId) -> Option<&mut SceneObject> {
+        self.objects.iter_mut().find(|o| o.id == id)
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
     }
 
-    fn get_all_triangles(&self) -> Vec<g3d::Triagnle3> {
-        // todo: move it out of here to the Shape trait
-        self.shapes.triangles
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    pub fn lighting_mut(&mut self) -> &mut Lighting {
+        &mut self.lighting
+    }
+
+    pub fn cull_mode(&self, id: ShapeId) -> Option<CullMode> {
+        self.object(id).map(|o| o.cull_mode)
+    }
+
+    /// Sets which sides of triangles of the shape are not drawn,
+    /// returns false if there is no shape with such id
+    pub fn set_cull_mode(&mut self, id: ShapeId, cull_mode: CullMode) -> bool {
+        match self.object_mut(id) {
+            Some(object) => {
+                object.cull_mode = cull_mode;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn get_all_triangles(&self) -> Vec<Face> {
+        self.objects
             .iter()
-            .map(|t| t.rotate(&self.shapes.axis_point, &self.shapes.axis_vector, self.shapes.angle))
-            .map(|t| t.shift(&self.shapes.intersept))
+            .enumerate()
+            .flat_map(|(object, o)| {
+                o.shape.approximate().into_iter().map(move |triangle| {
+                    let color = match triangle.color() {
+                        Some(color) => o.material.apply_opacity(color),
+                        None => o.material.fill_color(),
+                    };
+                    Face {
+                        object,
+                        triangle,
+                        color,
+                    }
+                })
+            })
             .collect()
     }
 
-    fn build_graph(&self, triangles_repo: &Vec<g3d::Triagnle3>) -> HashMap<usize, HashSet<usize>> {
-        let mut graph: HashMap<usize, HashSet<usize>> = HashMap::new();
-        for i in 0..triangles_repo.len() {
-            for j in 0..triangles_repo.len() {
-                if i != j && triangles_repo[i].is_above(&triangles_repo[j]) {
-                    let set = graph.entry(i).or_default();
-                    (*set).insert(j);
-                }
+    /// Whether the camera looks at the front side of the face,
+    /// None if the side the camera looks at is culled
+    fn visible_side(&self, face: &Face) -> Option<bool> {
+        let facing = self
+            .camera
+            .is_in_front_of(&g3d::Plane::from_triangle(&face.triangle));
+        let visible = match self.objects[face.object].cull_mode {
+            CullMode::None => true,
+            CullMode::Back => facing,
+            CullMode::Front => !facing,
+        };
+        if visible {
+            Some(facing)
+        } else {
+            None
+        }
+    }
+
+    /// Pieces of the faces inside the view frustum of the camera (see `Camera::clip`),
+    /// only the near and far planes cut faces if `sides` is false
+    fn clip(&self, faces: &[Face], sides: bool) -> Vec<Face> {
+        faces
+            .iter()
+            .flat_map(|f| {
+                let pieces = if sides {
+                    self.camera.clip(&f.triangle)
+                } else {
+                    self.camera.clip_depth(&f.triangle)
+                };
+                pieces.into_iter().map(move |triangle| Face {
+                    object: f.object,
+                    triangle,
+                    color: f.color,
+                })
+            })
+            .collect()
+    }
+
+    /// Visible faces in the screen coordinates, colored by the lights of the scene.
+    /// Faces are clipped by the view frustum, so faces crossing its planes are replaced
+    /// by their pieces. The side of a face which the camera looks at is lit,
+    /// so faces which are not culled look the same from both sides
+    fn to_screen(&self, faces: &[Face]) -> Vec<Face> {
+        self.clip(faces, true)
+            .iter()
+            .filter_map(|f| {
+                let facing = self.visible_side(f)?;
+                let normal = f.triangle.normal();
+                let normal = if facing { normal } else { normal.neg() };
+                Some(Face {
+                    object: f.object,
+                    triangle: self.camera.to_screen_triangle(&f.triangle)?,
+                    color: self.lighting.shade(f.color, &normal),
+                })
+            })
+            .collect()
+    }
+
+    /// Center of the bounding box of all triangles of the scene
+    pub fn center(&self) -> Point3 {
+        let points: Vec<Point3> = self
+            .get_all_triangles()
+            .iter()
+            .flat_map(|f| vec![f.triangle.p1, f.triangle.p2, f.triangle.p3])
+            .collect();
+        if points.is_empty() {
+            return Point3::new(0., 0., 0.);
+        }
+        let min = |f: fn(&Point3) -> f64| points.iter().map(f).fold(f64::INFINITY, f64::min);
+        let max = |f: fn(&Point3) -> f64| points.iter().map(f).fold(f64::NEG_INFINITY, f64::max);
+        Point3::new(
+            (min(|p| p.x) + max(|p| p.x)) / 2.,
+            (min(|p| p.y) + max(|p| p.y)) / 2.,
+            (min(|p| p.z) + max(|p| p.z)) / 2.,
+        )
+    }
+
+    /// Edges go from a triangle to triangles which are below it.
+    /// Triangles can be above each other only if their projections intersect,
+    /// so only pairs with overlapping bounding boxes of projections are compared
+    fn build_graph(&self, faces: &[Face]) -> Vec<Vec<usize>> {
+        let mut graph: Vec<Vec<usize>> = vec![Vec::new(); faces.len()];
+        let projected: Vec<(usize, g2d::Rect)> = faces
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, f)| Some((idx, f.triangle.project()?.bounding_box())))
+            .collect();
+        let rects: Vec<g2d::Rect> = projected.iter().map(|(_, rect)| *rect).collect();
+        for (a, b) in g2d::overlapping_pairs(&rects) {
+            let (i, j) = (projected[a].0, projected[b].0);
+            if faces[i].triangle.is_above(&faces[j].triangle) {
+                graph[i].push(j);
             }
+            if faces[j].triangle.is_above(&faces[i].triangle) {
+                graph[j].push(i);
+            }
+        }
+        // same order of edges as comparing all pairs, so the order of painting doesn't change
+        for edges in graph.iter_mut() {
+            edges.sort_unstable();
         }
-        // log_js(&format!("graph={:?}", &graph));
         graph
     }
 
-    fn dfs(
-        &self,
-        graph: &HashMap<usize, HashSet<usize>>,
-        mut order: &mut Vec<usize>,
-        mut vis: &mut HashSet<usize>,
-        node: usize,
-    ) {
-        if vis.contains(&node) {
-            return;
+    /// Returns faces in the order of painting.
+    /// Faces which are above each other in a cycle are split along planes of each other
+    /// until the cycle disappears or `MAX_SPLIT_ROUNDS` is reached
+    fn order(&self, mut faces: Vec<Face>) -> Vec<Face> {
+        let mut components = strongly_connected_components(&self.build_graph(&faces));
+        for _ in 0..MAX_SPLIT_ROUNDS {
+            let mut split = false;
+            let mut replaced: HashMap<usize, Vec<Face>> = HashMap::new();
+            for component in components.iter().filter(|c| c.len() > 1) {
+                if let Some(pieces) = Self::split_cycle(&faces, component) {
+                    replaced.extend(pieces);
+                    split = true;
+                }
+            }
+            if !split {
+                break;
+            }
+            faces = faces
+                .into_iter()
+                .enumerate()
+                .flat_map(|(idx, f)| replaced.remove(&idx).unwrap_or_else(|| vec![f]))
+                .collect();
+            components = strongly_connected_components(&self.build_graph(&faces));
         }
-        vis.insert(node);
-        for &next_node in graph.get(&node).unwrap_or(&HashSet::new()).iter() {
-            self.dfs(graph, &mut order, &mut vis, next_node);
+
+        components
+            .iter()
+            .flatten()
+            .map(|&idx| faces[idx].clone())
+            .collect()
+    }
+
+    /// Finds a face of the cycle whose plane crosses other faces of the cycle
+    /// and returns pieces of the crossed faces by their indexes
+    fn split_cycle(faces: &[Face], component: &[usize]) -> Option<HashMap<usize, Vec<Face>>> {
+        for &splitter in component.iter() {
+            let plane = g3d::Plane::from_triangle(&faces[splitter].triangle);
+            let pieces: HashMap<usize, Vec<Face>> = component
+                .iter()
+                .filter(|&&idx| idx != splitter)
+                .filter_map(|&idx| {
+                    let (mut front, back) = faces[idx].split(&plane);
+                    if front.is_empty() || back.is_empty() {
+                        None
+                    } else {
+                        front.extend(back);
+                        Some((idx, front))
+                    }
+                })
+                .collect();
+            if !pieces.is_empty() {
+                return Some(pieces);
+            }
         }
-        order.push(node);
+        None
     }
 
-    fn get_ordered_projection(&self) -> Vec<g2d::Triangle2> {
-        let triangles_repo = self.get_all_triangles();
-        let mut graph = self.build_graph(&triangles_repo);
-        let mut order: Vec<usize> = Vec::new();
-        let mut vis: HashSet<usize> = HashSet::new();
+    /// Back to front order of faces taken from the BSP tree,
+    /// the tree is rebuilt if triangles of the scene have changed since the last call
+    fn bsp_order(&self, faces: Vec<Face>) -> Vec<Face> {
+        let mut bsp = self.bsp.borrow_mut();
+        match bsp.as_ref() {
+            Some((source, _)) if *source == faces => {}
+            _ => {
+                let tree = BspTree::new(faces.clone());
+                *bsp = Some((faces, tree));
+            }
+        }
+        bsp.as_ref().unwrap().1.back_to_front(&self.camera)
+    }
 
-        for node in 0..triangles_repo.len() {
-            self.dfs(&graph, &mut order, &mut vis, node)
+    /// Visible faces in the screen coordinates in the order of painting
+    fn get_ordered_faces(&self) -> Vec<Face> {
+        let faces = self.get_all_triangles();
+        match self.ordering_mode {
+            OrderingMode::Graph => self.order(self.to_screen(&faces)),
+            // the tree doesn't depend on the camera, so faces are culled after the traversal
+            OrderingMode::Bsp => self.to_screen(&self.bsp_order(faces)),
         }
+    }
 
-        order
+    /// Projections of visible faces in the order of painting with their colors and objects.
+    /// Projections are clipped by the viewport, so a triangle may be replaced by its pieces
+    /// and coordinates of far away points don't overflow the integers of `render`
+    fn get_projected_faces(&self) -> Vec<(g2d::Triangle2, Color, &SceneObject)> {
+        let (width, height) = self.camera.viewport();
+        let viewport = g2d::Rect::new(
+            g2d::Point2::new(0., 0.),
+            g2d::Point2::new(width as f64, height as f64),
+        );
+        self.get_ordered_faces()
             .iter()
-            .map(|&idx| triangles_repo.get(idx).unwrap().project())
-            .filter(|opt| opt.is_some())
-            .map(|opt| opt.unwrap())
+            .filter_map(|f| Some((f.triangle.project()?, f)))
+            .flat_map(|(triangle, f)| {
+                triangle
+                    .clip(&viewport)
+                    .into_iter()
+                    .map(move |piece| (piece, f.color, &self.objects[f.object]))
+            })
             .collect()
     }
 
-    pub fn render(&self) -> *const i32 {
-        let mut buf: Vec<i32> = vec![0];
-        let triangles = self.get_ordered_projection();
+    #[cfg(test)]
+    fn get_ordered_projection(&self) -> Vec<g2d::Triangle2> {
+        self.get_projected_faces()
+            .into_iter()
+            .map(|(triangle, _, _)| triangle)
+            .collect()
+    }
+
+    /// Writes the current frame into `buf`, replacing its previous content.
+    /// Layout: number of triangles followed by `RENDER_STRIDE` numbers for every triangle:
+    /// x1, y1, x2, y2, x3, y3, rgba of the fill, rgba of the stroke and the stroke width.
+    /// Coordinates are rounded to the closest pixel, the stroke width is given
+    /// in hundredths of a pixel and is 0 when edges are not visible
+    pub fn render(&self, buf: &mut Vec<i32>) {
+        let faces = self.get_projected_faces();
 
-        buf[0] = triangles.len() as i32;
+        buf.clear();
+        buf.push(faces.len() as i32);
+
+        for (tri, fill, object) in faces.iter() {
+            let material = &object.material;
+            buf.push(tri.p1.x.round() as i32);
+            buf.push(tri.p1.y.round() as i32);
+            buf.push(tri.p2.x.round() as i32);
+            buf.push(tri.p2.y.round() as i32);
+            buf.push(tri.p3.x.round() as i32);
+            buf.push(tri.p3.y.round() as i32);
+            let stroke = material.stroke_color();
+            for color in [fill, &stroke].iter() {
+                buf.push(color.r as i32);
+                buf.push(color.g as i32);
+                buf.push(color.b as i32);
+                buf.push(color.a as i32);
+            }
+            let stroke_width = if material.edges_visible {
+                (material.stroke_width * 100.).round() as i32
+            } else {
+                0
+            };
+            buf.push(stroke_width);
+        }
+    }
+
+    /// SVG document of the current frame of the size of the viewport,
+    /// triangles are painted in the same order and with the same colors as by `render`.
+    /// See `svg::document` for `group_by_shape`
+    pub fn to_svg(&self, group_by_shape: bool) -> String {
+        let triangles: Vec<svg::SvgTriangle> = self
+            .get_projected_faces()
+            .into_iter()
+            .map(|(triangle, fill, object)| svg::SvgTriangle {
+                triangle,
+                fill,
+                material: &object.material,
+                shape: object.id,
+            })
+            .collect();
+        let (width, height) = self.camera.viewport();
+        svg::document(width, height, &triangles, group_by_shape)
+    }
+
+    /// Draws the current frame into the frame buffer using its depth buffer,
+    /// so the result doesn't depend on the order of triangles
+    pub fn rasterize(&self, frame: &mut FrameBuffer) {
+        frame.clear(Color::transparent());
+        let perspective = self.camera.projection() == Projection::Perspective;
+        // pixels out of the frame are skipped anyway and vertex lights of pieces
+        // would change Gouraud shading, so only the near and far planes cut triangles here
+        for face in self.clip(&self.get_all_triangles(), false).iter() {
+            let facing = match self.visible_side(face) {
+                Some(facing) => facing,
+                None => continue,
+            };
+            let screen = match self.camera.to_screen_triangle(&face.triangle) {
+                Some(screen) => screen,
+                None => continue,
+            };
+            let sign = if facing { 1. } else { -1. };
+            let normals = face.triangle.vertex_normals().map(|n| n.scale(sign));
+
+            // barycentric coordinates of the pixel on the triangle in the world
+            let world_weights = |w: [f64; 3]| {
+                if perspective {
+                    raster::perspective_correct(&screen, w)
+                } else {
+                    w
+                }
+            };
+            let material = &self.objects[face.object].material;
+            let triangle = &face.triangle;
+            let base = |w: [f64; 3]| match (&material.texture, &triangle.uvs, &triangle.colors) {
+                (Some(texture), Some(uvs), _) => {
+                    material.apply_opacity(texture.sample(&g3d::interpolate_uv(uvs, w)))
+                }
+                (_, _, Some(colors)) => material.apply_opacity(Color::mix(colors, w)),
+                _ => face.color,
+            };
+            let light: Box<dyn Fn([f64; 3]) -> [f64; 3]> = match self.shading_mode {
+                ShadingMode::Flat => {
+                    let light = self.lighting.light(&face.triangle.normal().scale(sign));
+                    Box::new(move |_| light)
+                }
+                ShadingMode::Gouraud => {
+                    let lights = normals.map(|n| self.lighting.light(&n));
+                    Box::new(move |w| {
+                        let channel = |i: usize| (0..3).map(|k| lights[k][i] * w[k]).sum();
+                        [channel(0), channel(1), channel(2)]
+                    })
+                }
+                ShadingMode::Phong => {
+                    Box::new(move |w| self.lighting.light(&g3d::interpolate(&normals, w)))
+                }
+            };
 
-        for tri in triangles.iter() {
-            buf.push(tri.p1.x);
-            buf.push(tri.p1.y);
-            buf.push(tri.p2.x);
-            buf.push(tri.p2.y);
-            buf.push(tri.p3.x);
-            buf.push(tri.p3.y);
+            frame.draw_shaded_triangle(&screen, |w| {
+                let w = world_weights(w);
+                light::illuminate(base(w), light(w))
+            });
         }
-        buf.as_ptr()
     }
 
     pub fn step(&mut self) {
-        self.shapes.step();
+        for object in self.objects.iter_mut() {
+            object.shape.step();
+        }
     }
 }
 
+/// Tarjan's algorithm.
+/// Components are returned in the reverse topological order:
+/// if there is an edge from `a` to `b`, component of `b` goes first
+fn strongly_connected_components(graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        graph: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low_link: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next_index: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, node: usize) {
+        state.index[node] = Some(state.next_index);
+        state.low_link[node] = state.next_index;
+        state.next_index += 1;
+        state.stack.push(node);
+        state.on_stack[node] = true;
+
+        for &next_node in state.graph[node].iter() {
+            match state.index[next_node] {
+                None => {
+                    visit(state, next_node);
+                    state.low_link[node] = state.low_link[node].min(state.low_link[next_node]);
+                }
+                Some(next_index) if state.on_stack[next_node] => {
+                    state.low_link[node] = state.low_link[node].min(next_index);
+                }
+                _ => {}
+            }
+        }
+
+        if Some(state.low_link[node]) == state.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = state.stack.pop() {
+                state.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        graph,
+        index: vec![None; graph.len()],
+        low_link: vec![0; graph.len()],
+        on_stack: vec![false; graph.len()],
+        stack: Vec::new(),
+        next_index: 0,
+        components: Vec::new(),
+    };
+    for node in 0..graph.len() {
+        if state.index[node].is_none() {
+            visit(&mut state, node);
+        }
+    }
+    state.components
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use g3d::Vector3;
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new(
+            Point3::new(100., 100., 100.),
+            Point3::new(100., 100., 0.),
+            Vector3::new(0., -1., 0.),
+            200,
+            200,
+        );
+        camera.set_projection(camera::Projection::Orthographic);
+        camera
+    }
 
     #[test]
     fn ordered_triangles() {
         let t1 = g3d::Triagnle3::new(
-            g3d::Point3::new(0, 0, 0),
-            g3d::Point3::new(-1, 1, 0),
-            g3d::Point3::new(1, 1, 0),
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(99., 101., 0.),
+            g3d::Point3::new(101., 101., 0.),
         );
         let t2 = g3d::Triagnle3::new(
-            g3d::Point3::new(0, 0, 1),
-            g3d::Point3::new(-1, 1, 1),
-            g3d::Point3::new(1, 1, 1),
+            g3d::Point3::new(100., 100., 1.),
+            g3d::Point3::new(99., 101., 1.),
+            g3d::Point3::new(101., 101., 1.),
         );
         let t3 = g3d::Triagnle3::new(
-            g3d::Point3::new(0, 0, -1),
-            g3d::Point3::new(-1, 1, -1),
-            g3d::Point3::new(1, 1, -1),
+            g3d::Point3::new(100., 100., -1.),
+            g3d::Point3::new(99., 101., -1.),
+            g3d::Point3::new(101., 101., -1.),
         );
         let t4 = g3d::Triagnle3::new(
-            g3d::Point3::new(0, 0, -10),
-            g3d::Point3::new(-1, -1, -10),
-            g3d::Point3::new(1, -1, -10),
-        );
-        let shapes = SetOfTriangles::new(
-            vec![t1.clone(), t2.clone(), t3.clone(), t4.clone()],
-            Point3::new(0, 0, 0),
-            Vector3::new(1, 1, 1),
-            0.0,
-            Vector3::new(0, 0, 0),
-        );
-        let scene = SceneTmp::new(shapes);
+            g3d::Point3::new(100., 98., -10.),
+            g3d::Point3::new(97., 102., -10.),
+            g3d::Point3::new(103., 102., -10.),
+        );
+        let shapes = SetOfTriangles::new(vec![t1.clone(), t2.clone(), t3.clone(), t4.clone()]);
+        let mut scene = SceneTmp::new(camera());
+        scene.add_shape(Box::new(Animated::new(
+            shapes,
+            Transform::new(
+                Point3::new(0., 0., 0.),
+                Vector3::new(1., 1., 1.),
+                0.0,
+                Vector3::new(0., 0., 0.),
+            ),
+            Animation::still(),
+        )));
+        let project = |t: &g3d::Triagnle3| {
+            scene
+                .camera()
+                .to_screen_triangle(t)
+                .unwrap()
+                .project()
+                .unwrap()
+        };
+        assert_eq!(
+            scene.get_ordered_projection(),
+            vec![project(&t4), project(&t3), project(&t1), project(&t2)]
+        );
+    }
+
+    #[test]
+    fn triangles_of_different_shapes_are_ordered_together() {
+        let t1 = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(99., 101., 0.),
+            g3d::Point3::new(101., 101., 0.),
+        );
+        let t2 = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 1.),
+            g3d::Point3::new(99., 101., 1.),
+            g3d::Point3::new(101., 101., 1.),
+        );
+        let t3 = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 2.),
+            g3d::Point3::new(99., 101., 2.),
+            g3d::Point3::new(101., 101., 2.),
+        );
+        let mut scene = SceneTmp::new(camera());
+        let first = scene.add_shape(Box::new(SetOfTriangles::new(vec![t1.clone(), t3.clone()])));
+        let second = scene.add_shape(Box::new(Animated::new(
+            SetOfTriangles::new(vec![t1.clone()]),
+            Transform::new(
+                Point3::new(0., 0., 0.),
+                Vector3::new(0., 0., 1.),
+                0.,
+                Vector3::new(0., 0., 1.),
+            ),
+            Animation::still(),
+        )));
+        assert_ne!(first, second);
+
+        let camera = scene.camera().clone();
+        let project = |t: &g3d::Triagnle3| camera.to_screen_triangle(t).unwrap().project().unwrap();
         assert_eq!(
             scene.get_ordered_projection(),
-            vec![
-                t2.project().unwrap(),
-                t1.project().unwrap(),
-                t3.project().unwrap(),
-                t4.project().unwrap()
-            ]
+            vec![project(&t1), project(&t2), project(&t3)]
+        );
+
+        assert!(scene.remove_shape(first).is_some());
+        assert!(scene.remove_shape(first).is_none());
+        assert!(scene.shape(first).is_none());
+        assert_eq!(scene.shape_ids(), vec![second]);
+        assert_eq!(scene.get_ordered_projection(), vec![project(&t2)]);
+    }
+
+    #[test]
+    fn shapes_are_animated_independently() {
+        let triangle = g3d::Triagnle3::new(
+            g3d::Point3::new(0., 0., 0.),
+            g3d::Point3::new(-1., 1., 0.),
+            g3d::Point3::new(1., 1., 0.),
+        );
+        let mut scene = SceneTmp::new(camera());
+        let moving = scene.add_shape(Box::new(Animated::new(
+            SetOfTriangles::new(vec![triangle.clone()]),
+            Transform::identity(),
+            Animation::new(0., 2.),
+        )));
+        let still = scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle.clone()])));
+        scene.step();
+        scene.step();
+
+        assert_eq!(
+            scene.shape(moving).unwrap().approximate(),
+            vec![triangle.shift(&Vector3::new(4., 4., 4.))]
         );
+        assert_eq!(scene.shape(still).unwrap().approximate(), vec![triangle]);
+    }
+
+    #[test]
+    fn components_in_reverse_topological_order() {
+        let graph = vec![vec![1], vec![2], vec![1, 3], vec![], vec![0]];
+        assert_eq!(
+            strongly_connected_components(&graph),
+            vec![vec![3], vec![2, 1], vec![0], vec![4]]
+        );
+    }
+
+    fn faces(triangles: Vec<g3d::Triagnle3>) -> Vec<Face> {
+        triangles
+            .into_iter()
+            .map(|triangle| Face {
+                object: 0,
+                triangle,
+                color: Material::default().fill,
+            })
+            .collect()
+    }
+
+    /// Z of the triangle at (x, y) if the point is strictly inside its projection
+    fn height_at(t: &g3d::Triagnle3, x: f64, y: f64) -> Option<f64> {
+        let (a, b, c) = (t.p1, t.p2, t.p3);
+        let det = (b.y - c.y) * (a.x - c.x) + (c.x - b.x) * (a.y - c.y);
+        let l1 = ((b.y - c.y) * (x - c.x) + (c.x - b.x) * (y - c.y)) / det;
+        let l2 = ((c.y - a.y) * (x - c.x) + (a.x - c.x) * (y - c.y)) / det;
+        let l3 = 1. - l1 - l2;
+        if l1 > 1e-6 && l2 > 1e-6 && l3 > 1e-6 {
+            Some(l1 * a.z + l2 * b.z + l3 * c.z)
+        } else {
+            None
+        }
+    }
+
+    /// Orders the triangles in the mode and checks on a grid of points over them
+    /// that the triangle painted last is the highest one, world z works as the height
+    /// since the camera looks along -z
+    fn assert_painted_in_order(
+        triangles: Vec<g3d::Triagnle3>,
+        mode: OrderingMode,
+        camera: &Camera,
+    ) {
+        let ordered = match mode {
+            OrderingMode::Graph => SceneTmp::new(camera.clone()).order(faces(triangles)),
+            OrderingMode::Bsp => bsp::BspTree::new(faces(triangles)).back_to_front(camera),
+        };
+        // the triangles cross each other, so some of them have to be split
+        assert!(ordered.len() > 2);
+
+        for i in 0..48 {
+            for j in 0..48 {
+                let (x, y) = (0.1 + i as f64 / 4., 0.1 + j as f64 / 4.);
+                let heights: Vec<f64> = ordered
+                    .iter()
+                    .filter_map(|f| height_at(&f.triangle, x, y))
+                    .collect();
+                if let Some(painted) = heights.last() {
+                    let top = heights.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    assert!(painted + 1e-9 >= top, "wrong order at ({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    /// Each triangle has a vertex inside the other one where it is below the other one
+    fn interpenetrating_triangles() -> Vec<g3d::Triagnle3> {
+        vec![
+            g3d::Triagnle3::new(
+                g3d::Point3::new(9., 3., 0.),
+                g3d::Point3::new(0., 0., 0.),
+                g3d::Point3::new(0., 10., 0.),
+            ),
+            g3d::Triagnle3::new(
+                g3d::Point3::new(2., 2., -5.),
+                g3d::Point3::new(12., 2., 5.),
+                g3d::Point3::new(2., 12., -5.),
+            ),
+        ]
+    }
+
+    #[test]
+    fn interpenetrating_triangles_are_split() {
+        let triangles = interpenetrating_triangles();
+        // so they are above each other
+        assert!(triangles[0].is_above(&triangles[1]) && triangles[1].is_above(&triangles[0]));
+        assert_painted_in_order(triangles, OrderingMode::Graph, &camera());
+    }
+
+    #[test]
+    fn bsp_ordering() {
+        let mut camera = camera();
+        for projection in [
+            camera::Projection::Orthographic,
+            camera::Projection::Perspective,
+        ] {
+            camera.set_projection(projection);
+            assert_painted_in_order(interpenetrating_triangles(), OrderingMode::Bsp, &camera);
+        }
+    }
+
+    #[test]
+    fn bsp_mode_matches_graph_mode() {
+        let triangles: Vec<g3d::Triagnle3> = [0., 1., -1., -10.]
+            .iter()
+            .map(|&z| {
+                g3d::Triagnle3::new(
+                    g3d::Point3::new(100., 100., z),
+                    g3d::Point3::new(99., 101., z),
+                    g3d::Point3::new(101., 101., z),
+                )
+            })
+            .collect();
+        let mut scene = SceneTmp::new(camera());
+        scene.add_shape(Box::new(SetOfTriangles::new(triangles)));
+        let by_graph = scene.get_ordered_projection();
+        assert_eq!(by_graph.len(), 4);
+        scene.set_ordering_mode(OrderingMode::Bsp);
+        assert_eq!(scene.get_ordered_projection(), by_graph);
+        // the tree is reused while the scene doesn't change
+        assert_eq!(scene.get_ordered_projection(), by_graph);
+    }
+
+    #[test]
+    fn bsp_of_a_large_convex_mesh() {
+        // faces of a convex mesh are behind each other, so the tree is a chain of all of them,
+        // it is built and traversed in a thread with a stack too small for a recursion that deep
+        let ordered = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let center = Point3::new(100., 100., 0.);
+                let (rings, segments) = (24, 40);
+                let point = |ring: usize, segment: usize| {
+                    let theta = std::f64::consts::PI * ring as f64 / rings as f64;
+                    let phi = 2. * std::f64::consts::PI * segment as f64 / segments as f64;
+                    let direction = Vector3::new(
+                        theta.sin() * phi.cos(),
+                        theta.sin() * phi.sin(),
+                        theta.cos(),
+                    );
+                    center.add(&direction.scale(50.))
+                };
+                let mut triangles = Vec::new();
+                for ring in 0..rings {
+                    for segment in 0..segments {
+                        let (a, b) = (point(ring, segment), point(ring, segment + 1));
+                        let (c, d) = (point(ring + 1, segment), point(ring + 1, segment + 1));
+                        triangles.push(g3d::Triagnle3::new(a, b, d));
+                        triangles.push(g3d::Triagnle3::new(a, d, c));
+                    }
+                }
+                bsp::BspTree::new(faces(triangles)).back_to_front(&camera())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert!(ordered.len() > 1000);
+        // faces looking away from the camera are painted before the ones looking at it
+        let center = Point3::new(100., 100., 0.);
+        let outward_z = |f: &Face| {
+            let t = &f.triangle;
+            let normal = t.normal();
+            let centroid = t.p1.add(&t.p2).add(&t.p3).scale(1. / 3.);
+            normal.z * normal.dot_product(&centroid.sub(&center)).signum()
+        };
+        let last_back = ordered.iter().rposition(|f| outward_z(f) < 0.).unwrap();
+        let first_front = ordered.iter().position(|f| outward_z(f) > 0.).unwrap();
+        assert!(last_back < first_front);
+    }
+
+    #[test]
+    fn culled_faces_are_not_drawn() {
+        // the camera looks along -z, so the normal of `facing` points to the camera
+        let facing = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(99., 101., 0.),
+            g3d::Point3::new(101., 101., 0.),
+        );
+        assert!(facing.normal().z > 0.);
+        let turned =
+            g3d::Triagnle3::new(facing.p1, facing.p3, facing.p2).shift(&Vector3::new(0., 0., 1.));
+        let mut scene = SceneTmp::new(camera());
+        let id = scene.add_shape(Box::new(SetOfTriangles::new(vec![
+            facing.clone(),
+            turned.clone(),
+        ])));
+        let camera = scene.camera().clone();
+        let project = |t: &g3d::Triagnle3| camera.to_screen_triangle(t).unwrap().project().unwrap();
+
+        for mode in [OrderingMode::Graph, OrderingMode::Bsp] {
+            scene.set_ordering_mode(mode);
+            assert!(scene.set_cull_mode(id, CullMode::None));
+            assert_eq!(scene.get_ordered_projection().len(), 2);
+            scene.set_cull_mode(id, CullMode::Back);
+            assert_eq!(scene.get_ordered_projection(), vec![project(&facing)]);
+            scene.set_cull_mode(id, CullMode::Front);
+            assert_eq!(scene.get_ordered_projection(), vec![project(&turned)]);
+        }
+        assert!(!scene.set_cull_mode(id + 1, CullMode::Back));
+    }
+
+    #[test]
+    fn faces_are_shaded_in_render_buffer() {
+        let flat = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(150., 100., 0.),
+            g3d::Point3::new(100., 150., 0.),
+        );
+        // looks away from the camera, but is lit from its visible side
+        let tilted = g3d::Triagnle3::new(
+            g3d::Point3::new(50., 50., 0.),
+            g3d::Point3::new(50., 80., 30.),
+            g3d::Point3::new(80., 50., 0.),
+        );
+        let mut scene = SceneTmp::new(camera());
+        scene.add_shape(Box::new(SetOfTriangles::new(vec![flat, tilted])));
+        let white = Color::rgb(255, 255, 255);
+        *scene.lighting_mut() = Lighting::new(
+            light::AmbientLight::new(white, 0.5),
+            vec![light::DirectionalLight::new(
+                Vector3::new(0., 0., -1.),
+                white,
+                0.5,
+            )],
+        );
+
+        let mut buf = Vec::new();
+        scene.render(&mut buf);
+        assert_eq!(buf.len(), 1 + 2 * RENDER_STRIDE);
+        let colors: Vec<&[i32]> = buf[1..].chunks(RENDER_STRIDE).map(|t| &t[6..10]).collect();
+        let cos = 1. / 2_f64.sqrt();
+        let lit = |c: u8| (c as f64 * (0.5 + 0.5 * cos)).round() as i32;
+        assert!(colors.contains(&&[0, 240, 15, 255][..]));
+        assert!(colors.contains(&&[0, lit(240), lit(15), 255][..]));
+    }
+
+    #[test]
+    fn smooth_shading() {
+        // flat triangle facing the camera, its vertex normals lean away from the light
+        // except the first one
+        let triangle = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(100., 180., 0.),
+            g3d::Point3::new(180., 100., 0.),
+        )
+        .with_normals([
+            Vector3::new(0., 0., 1.),
+            Vector3::new(0., 1., 1.),
+            Vector3::new(1., 0., 1.),
+        ]);
+        let mut scene = SceneTmp::new(camera());
+        scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle])));
+        let white = Color::rgb(255, 255, 255);
+        *scene.lighting_mut() = Lighting::new(
+            light::AmbientLight::new(white, 0.),
+            vec![light::DirectionalLight::new(
+                Vector3::new(0., 0., -1.),
+                white,
+                1.,
+            )],
+        );
+
+        let mut frame = FrameBuffer::new(200, 200);
+        let camera = scene.camera().clone();
+        let mut green = |mode: ShadingMode, x: f64, y: f64| {
+            scene.set_shading_mode(mode);
+            scene.rasterize(&mut frame);
+            let p = camera.to_screen(&g3d::Point3::new(x, y, 0.)).unwrap();
+            frame.pixel(p.x as usize, p.y as usize).g as i32
+        };
+        assert_eq!(green(ShadingMode::Flat, 101., 101.), 240);
+        assert_eq!(green(ShadingMode::Flat, 138., 138.), 240);
+        assert!(green(ShadingMode::Gouraud, 101., 101.) > 235);
+        assert!(green(ShadingMode::Phong, 101., 101.) > 235);
+        // near the middle of the edge opposite to the first vertex
+        let gouraud = green(ShadingMode::Gouraud, 138., 138.);
+        let phong = green(ShadingMode::Phong, 138., 138.);
+        assert!((165..180).contains(&gouraud), "{}", gouraud);
+        assert!((190..210).contains(&phong), "{}", phong);
+    }
+
+    #[test]
+    fn materials_in_render_buffer() {
+        let triangle = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(150., 100., 0.),
+            g3d::Point3::new(100., 150., 0.),
+        );
+        let mut scene = SceneTmp::new(camera());
+        *scene.lighting_mut() = Lighting::unlit();
+        let first = scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle.clone()])));
+        let second = scene.add_shape(Box::new(SetOfTriangles::new(vec![
+            triangle.shift(&Vector3::new(0., 0., 1.))
+        ])));
+        {
+            let material = scene.material_mut(second).unwrap();
+            material.fill = Color::rgb(1, 2, 3);
+            material.stroke = Color::rgb(4, 5, 6);
+            material.opacity = 0.5;
+            material.stroke_width = 2.5;
+        }
+        scene.material_mut(first).unwrap().edges_visible = false;
+        assert!(scene.material(second + 1).is_none());
+
+        let mut buf = Vec::new();
+        scene.render(&mut buf);
+        let triangles: Vec<&[i32]> = buf[1..].chunks(RENDER_STRIDE).collect();
+        assert_eq!(triangles[0][6..], [0, 240, 15, 255, 0, 0, 0, 0, 0]);
+        assert_eq!(triangles[1][6..], [1, 2, 3, 128, 4, 5, 6, 128, 250]);
+    }
+
+    #[test]
+    fn svg_is_painted_like_render_buffer() {
+        let triangle = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(150., 100., 0.),
+            g3d::Point3::new(100., 150., 0.),
+        );
+        let mut scene = SceneTmp::new(camera());
+        *scene.lighting_mut() = Lighting::unlit();
+        let below = scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle.clone()])));
+        let above = scene.add_shape(Box::new(SetOfTriangles::new(vec![
+            triangle.shift(&Vector3::new(0., 0., 1.))
+        ])));
+        scene.material_mut(above).unwrap().fill = Color::rgb(1, 2, 3);
+
+        let svg = scene.to_svg(true);
+        assert!(svg.contains("viewBox=\"0 0 200 200\""));
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        let position = |text: &str| svg.find(text).unwrap();
+        assert!(position(&format!("shape-{}", below)) < position(&format!("shape-{}", above)));
+        assert!(position("fill=\"rgb(0,240,15)\"") < position("fill=\"rgb(1,2,3)\""));
+    }
+
+    #[test]
+    fn textured_triangles() {
+        let uv = g2d::Point2::new;
+        let (a, b, c, d) = (
+            g3d::Point3::new(60., 60., 0.),
+            g3d::Point3::new(140., 60., 0.),
+            g3d::Point3::new(140., 140., 0.),
+            g3d::Point3::new(60., 140., 0.),
+        );
+        let square = vec![
+            g3d::Triagnle3::new(a, b, c).with_uvs([uv(0., 0.), uv(1., 0.), uv(1., 1.)]),
+            g3d::Triagnle3::new(a, c, d).with_uvs([uv(0., 0.), uv(1., 1.), uv(0., 1.)]),
+        ];
+        let pixels = vec![
+            255, 0, 0, 255, 0, 255, 0, 255, //
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ];
+        let texture = texture::Texture::new(2, 2, pixels, texture::TextureFilter::Nearest);
+
+        let mut scene = SceneTmp::new(camera());
+        *scene.lighting_mut() = Lighting::unlit();
+        let id = scene.add_shape(Box::new(SetOfTriangles::new(square)));
+        scene.material_mut(id).unwrap().texture = texture.map(std::rc::Rc::new);
+
+        let camera = scene.camera().clone();
+        let mut frame = FrameBuffer::new(200, 200);
+        for projection in [Projection::Orthographic, Projection::Perspective] {
+            scene.camera_mut().set_projection(projection);
+            scene.rasterize(&mut frame);
+            let pixel = |x: f64, y: f64| {
+                let p = camera.to_screen(&g3d::Point3::new(x, y, 0.)).unwrap();
+                frame.pixel(p.x as usize, p.y as usize)
+            };
+            assert_eq!(pixel(70., 70.), Color::rgb(255, 0, 0));
+            assert_eq!(pixel(130., 70.), Color::rgb(0, 255, 0));
+            assert_eq!(pixel(70., 130.), Color::rgb(0, 0, 255));
+            assert_eq!(pixel(130., 130.), Color::rgb(255, 255, 255));
+        }
+    }
+
+    #[test]
+    fn vertex_colors_replace_fill() {
+        let (red, blue) = (Color::rgb(255, 0, 0), Color::rgb(0, 0, 255));
+        let triangle = g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(160., 100., 0.),
+            g3d::Point3::new(100., 160., 0.),
+        )
+        .with_colors([red, red, blue]);
+        let mut scene = SceneTmp::new(camera());
+        *scene.lighting_mut() = Lighting::unlit();
+        scene.add_shape(Box::new(SetOfTriangles::new(vec![triangle])));
+
+        let mut buf = Vec::new();
+        scene.render(&mut buf);
+        assert_eq!(buf[7..11], [170, 0, 85, 255]);
+
+        let camera = scene.camera().clone();
+        let mut frame = FrameBuffer::new(200, 200);
+        scene.rasterize(&mut frame);
+        let p = camera.to_screen(&g3d::Point3::new(101., 101., 0.)).unwrap();
+        assert!(frame.pixel(p.x as usize, p.y as usize).r > 240);
+        let p = camera.to_screen(&g3d::Point3::new(101., 155., 0.)).unwrap();
+        assert!(frame.pixel(p.x as usize, p.y as usize).b > 200);
+    }
+
+    #[test]
+    fn rasterized_frame() {
+        let mut scene = SceneTmp::new(camera());
+        scene.add_shape(Box::new(SetOfTriangles::new(vec![g3d::Triagnle3::new(
+            g3d::Point3::new(100., 100., 0.),
+            g3d::Point3::new(150., 100., 0.),
+            g3d::Point3::new(100., 150., 0.),
+        )])));
+        let mut frame = FrameBuffer::new(200, 200);
+        *scene.lighting_mut() = Lighting::unlit();
+        scene.rasterize(&mut frame);
+        assert_eq!(frame.pixel(105, 105), Material::default().fill);
+        assert_eq!(frame.pixel(95, 95), Color::transparent());
+    }
+
+    #[test]
+    fn graph_is_the_same_as_comparing_all_pairs() {
+        // pseudo-random triangles in the screen coordinates, some of them cross each other
+        let mut seed = 12345_u64;
+        let mut random = move |range: f64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1_u64 << 53) as f64 * range
+        };
+        let faces: Vec<Face> = (0..300)
+            .map(|_| {
+                let (x, y) = (random(1000.), random(700.));
+                let mut point = || g3d::Point3::new(x + random(80.), y + random(80.), random(10.));
+                Face {
+                    object: 0,
+                    triangle: g3d::Triagnle3::new(point(), point(), point()),
+                    color: Color::rgb(0, 0, 0),
+                }
+            })
+            .collect();
+
+        let mut expected: Vec<Vec<usize>> = vec![Vec::new(); faces.len()];
+        for i in 0..faces.len() {
+            for j in 0..faces.len() {
+                if i != j && faces[i].triangle.is_above(&faces[j].triangle) {
+                    expected[i].push(j);
+                }
+            }
+        }
+        let graph = SceneTmp::new(camera()).build_graph(&faces);
+        assert!(graph.iter().map(Vec::len).sum::<usize>() > 100);
+        assert_eq!(graph, expected);
     }
 }
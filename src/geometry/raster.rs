@@ -0,0 +1,200 @@
+use super::color::Color;
+use super::g3d::{Point3, Triagnle3};
+
+/// RGBA image with a depth buffer.
+/// Triangles are drawn in the screen coordinates (see `Camera`),
+/// a pixel is painted only if the triangle is closer than everything drawn there before
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    /// 4 bytes per pixel, rows from top to bottom
+    pixels: Vec<u8>,
+    /// the bigger the closer, as z of the screen coordinates
+    depth: Vec<f64>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+            depth: vec![f64::NEG_INFINITY; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let idx = (y * self.width + x) * 4;
+        let p = &self.pixels[idx..idx + 4];
+        Color::new(p[0], p[1], p[2], p[3])
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        for depth in self.depth.iter_mut() {
+            *depth = f64::NEG_INFINITY;
+        }
+    }
+
+    /// Fills pixels whose centers are inside the projection of the triangle
+    pub fn draw_triangle(&mut self, triangle: &Triagnle3, color: Color) {
+        self.draw_shaded_triangle(triangle, |_| color);
+    }
+
+    /// Fills pixels whose centers are inside the projection of the triangle,
+    /// color of a pixel is given by `shader` from barycentric coordinates of its center
+    pub fn draw_shaded_triangle<F: Fn([f64; 3]) -> Color>(
+        &mut self,
+        triangle: &Triagnle3,
+        shader: F,
+    ) {
+        let (p1, p2, p3) = (triangle.p1, triangle.p2, triangle.p3);
+        let area = edge(&p1, &p2, p3.x, p3.y);
+        if area == 0. {
+            return;
+        }
+
+        let min_x = p1.x.min(p2.x).min(p3.x).floor().max(0.) as usize;
+        let min_y = p1.y.min(p2.y).min(p3.y).floor().max(0.) as usize;
+        let max_x = (p1.x.max(p2.x).max(p3.x).ceil().max(0.) as usize).min(self.width);
+        let max_y = (p1.y.max(p2.y).max(p3.y).ceil().max(0.) as usize).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                // barycentric coordinates, all of them are non negative inside the triangle
+                let w1 = edge(&p2, &p3, px, py) / area;
+                let w2 = edge(&p3, &p1, px, py) / area;
+                let w3 = edge(&p1, &p2, px, py) / area;
+                if w1 < 0. || w2 < 0. || w3 < 0. {
+                    continue;
+                }
+
+                let z = w1 * p1.z + w2 * p2.z + w3 * p3.z;
+                let idx = y * self.width + x;
+                if z > self.depth[idx] {
+                    self.depth[idx] = z;
+                    let color = shader([w1, w2, w3]);
+                    self.pixels[idx * 4..idx * 4 + 4]
+                        .copy_from_slice(&[color.r, color.g, color.b, color.a]);
+                }
+            }
+        }
+    }
+}
+
+/// Barycentric coordinates of a point of the triangle in the world from its
+/// barycentric coordinates `w` on the screen. z of the screen triangle has to be
+/// the inverse of the depth, as with the perspective projection of `Camera`
+pub fn perspective_correct(triangle: &Triagnle3, w: [f64; 3]) -> [f64; 3] {
+    let a = w[0] * triangle.p1.z;
+    let b = w[1] * triangle.p2.z;
+    let c = w[2] * triangle.p3.z;
+    let sum = a + b + c;
+    [a / sum, b / sum, c / sum]
+}
+
+/// Doubled signed area of the triangle (a, b, p)
+fn edge(a: &Point3, b: &Point3, px: f64, py: f64) -> f64 {
+    (b.x - a.x) * (py - a.y) - (b.y - a.y) * (px - a.x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn square_half(z: f64) -> Triagnle3 {
+        Triagnle3::new(
+            Point3::new(0., 0., z),
+            Point3::new(4., 0., z),
+            Point3::new(0., 4., z),
+        )
+    }
+
+    #[test]
+    fn covers_pixel_centers() {
+        let mut frame = FrameBuffer::new(4, 4);
+        let red = Color::rgb(255, 0, 0);
+        frame.draw_triangle(&square_half(0.), red);
+        assert_eq!(frame.pixel(0, 0), red);
+        assert_eq!(frame.pixel(2, 1), red);
+        assert_eq!(frame.pixel(3, 3), Color::transparent());
+        assert_eq!(frame.pixel(2, 2), Color::transparent());
+    }
+
+    #[test]
+    fn closer_triangle_wins_regardless_of_order() {
+        let (red, green) = (Color::rgb(255, 0, 0), Color::rgb(0, 255, 0));
+        let mut frame = FrameBuffer::new(4, 4);
+        frame.draw_triangle(&square_half(1.), red);
+        frame.draw_triangle(&square_half(0.), green);
+        assert_eq!(frame.pixel(0, 0), red);
+
+        frame.clear(Color::transparent());
+        frame.draw_triangle(&square_half(0.), green);
+        frame.draw_triangle(&square_half(1.), red);
+        assert_eq!(frame.pixel(0, 0), red);
+    }
+
+    #[test]
+    fn shader_gets_barycentric_coordinates() {
+        let mut frame = FrameBuffer::new(4, 4);
+        frame.draw_shaded_triangle(&square_half(0.), |w| {
+            Color::rgb(
+                (w[0] * 255.) as u8,
+                (w[1] * 255.) as u8,
+                (w[2] * 255.) as u8,
+            )
+        });
+        // center of the pixel is (0.5, 0.5)
+        assert_eq!(frame.pixel(0, 0), Color::rgb(191, 31, 31));
+        assert_eq!(frame.pixel(2, 0), Color::rgb(63, 159, 31));
+    }
+
+    #[test]
+    fn perspective_correction() {
+        // the second vertex is three times further than the others
+        let triangle = Triagnle3::new(
+            Point3::new(0., 0., 1.),
+            Point3::new(4., 0., 1. / 3.),
+            Point3::new(0., 4., 1.),
+        );
+        // middle of the first edge on the screen is a quarter of it in the world
+        let w = perspective_correct(&triangle, [0.5, 0.5, 0.]);
+        assert!((w[0] - 0.75).abs() < 1e-9 && (w[1] - 0.25).abs() < 1e-9 && w[2] == 0.);
+        assert_eq!(
+            perspective_correct(&square_half(2.), [0.2, 0.3, 0.5]),
+            [0.2, 0.3, 0.5]
+        );
+    }
+
+    #[test]
+    fn interpenetrating_triangles() {
+        let (red, green) = (Color::rgb(255, 0, 0), Color::rgb(0, 255, 0));
+        let mut frame = FrameBuffer::new(4, 4);
+        frame.draw_triangle(&square_half(0.), red);
+        // goes from below to above the red one along x
+        let tilted = Triagnle3::new(
+            Point3::new(0., 0., -2.),
+            Point3::new(0., 4., -2.),
+            Point3::new(4., 0., 2.),
+        );
+        frame.draw_triangle(&tilted, green);
+        assert_eq!(frame.pixel(0, 1), red);
+        assert_eq!(frame.pixel(2, 0), green);
+    }
+}
@@ -0,0 +1,177 @@
+use super::g3d::{self, Point3, Triagnle3, Vector3};
+
+/// Anything which can be put into the scene
+pub trait Shape {
+    /// Triangles of the shape in the world coordinates
+    fn approximate(&self) -> Vec<Triagnle3>;
+
+    /// Called once per frame, animated shapes update their state here
+    fn step(&mut self) {}
+
+    /// Transform and animation which place the shape into the scene, if it has them
+    fn placement(&self) -> Option<(&Transform, &Animation)> {
+        None
+    }
+
+    /// Triangles before the transform given by `placement`
+    fn untransformed(&self) -> Vec<Triagnle3> {
+        self.approximate()
+    }
+}
+
+/// Position of a shape in the scene:
+/// rotation by `angle` around the axis followed by the shift by `intersept`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub axis_point: Point3,
+    pub axis_vector: Vector3,
+    pub angle: f64,
+    pub intersept: Vector3,
+}
+
+impl Transform {
+    pub fn new(axis_point: Point3, axis_vector: Vector3, angle: f64, intersept: Vector3) -> Self {
+        Transform {
+            axis_point,
+            axis_vector,
+            angle,
+            intersept,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new(
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 0., 1.),
+            0.,
+            Vector3::new(0., 0., 0.),
+        )
+    }
+
+    pub fn apply(&self, triangle: &Triagnle3) -> Triagnle3 {
+        triangle
+            .rotate(&self.axis_point, &self.axis_vector, self.angle)
+            .shift(&self.intersept)
+    }
+}
+
+/// Change of a transform made by every step of the scene
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub rotation_speed: f64,
+    pub move_speed: f64,
+}
+
+impl Animation {
+    pub fn new(rotation_speed: f64, move_speed: f64) -> Self {
+        Animation {
+            rotation_speed,
+            move_speed,
+        }
+    }
+
+    pub fn still() -> Self {
+        Animation::new(0., 0.)
+    }
+
+    fn step(&self, transform: &mut Transform) {
+        transform.angle += self.rotation_speed;
+        transform.intersept = transform.intersept.shift(self.move_speed);
+    }
+}
+
+/// Places any shape into the scene with its own transform and animation
+pub struct Animated<S: Shape> {
+    pub shape: S,
+    pub transform: Transform,
+    pub animation: Animation,
+}
+
+impl<S: Shape> Animated<S> {
+    pub fn new(shape: S, transform: Transform, animation: Animation) -> Self {
+        Animated {
+            shape,
+            transform,
+            animation,
+        }
+    }
+}
+
+impl<S: Shape> Shape for Animated<S> {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.shape
+            .approximate()
+            .iter()
+            .map(|t| self.transform.apply(t))
+            .collect()
+    }
+
+    fn step(&mut self) {
+        self.shape.step();
+        self.animation.step(&mut self.transform);
+    }
+
+    fn placement(&self) -> Option<(&Transform, &Animation)> {
+        Some((&self.transform, &self.animation))
+    }
+
+    fn untransformed(&self) -> Vec<Triagnle3> {
+        self.shape.approximate()
+    }
+}
+
+pub struct SetOfTriangles {
+    triangles: Vec<Triagnle3>,
+}
+
+impl SetOfTriangles {
+    pub fn new(triangles: Vec<Triagnle3>) -> Self {
+        SetOfTriangles { triangles }
+    }
+
+    /// Triangles without vertex normals get the ones averaged over triangles sharing the vertex,
+    /// see `g3d::smooth_normals`
+    pub fn with_smooth_normals(self) -> Self {
+        SetOfTriangles::new(g3d::smooth_normals(&self.triangles))
+    }
+}
+
+impl Shape for SetOfTriangles {
+    fn approximate(&self) -> Vec<Triagnle3> {
+        self.triangles.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn animated_shape() {
+        let triangle = Triagnle3::new(
+            Point3::new(1., 0., 0.),
+            Point3::new(0., 1., 0.),
+            Point3::new(0., 0., 0.),
+        );
+        let mut shape = Animated::new(
+            SetOfTriangles::new(vec![triangle.clone()]),
+            Transform::new(
+                Point3::new(0., 0., 0.),
+                Vector3::new(0., 0., 1.),
+                0.,
+                Vector3::new(1., 0., 0.),
+            ),
+            Animation::new(std::f64::consts::PI, 1.),
+        );
+        assert_eq!(
+            shape.approximate(),
+            vec![triangle.shift(&Vector3::new(1., 0., 0.))]
+        );
+
+        shape.step();
+        assert_eq!(shape.transform.angle, std::f64::consts::PI);
+        assert_eq!(shape.transform.intersept, Vector3::new(2., 1., 1.));
+        let moved = &shape.approximate()[0];
+        assert!(moved.p1.sub(&Point3::new(1., 1., 1.)).length() < 1e-9);
+    }
+}
@@ -0,0 +1,160 @@
+//! SVG documents of painted frames
+
+use std::fmt::Write;
+
+use super::color::Color;
+use super::g2d::{Point2, Triangle2};
+use super::material::Material;
+use super::ShapeId;
+
+/// Projected triangle with the paint of the painted frame
+pub struct SvgTriangle<'a> {
+    pub triangle: Triangle2,
+    pub fill: Color,
+    pub material: &'a Material,
+    pub shape: ShapeId,
+}
+
+/// SVG document of the size of the viewport with triangles in the order of painting.
+/// With `group_by_shape` consecutive triangles of the same shape are put into a `g` element
+/// with the class `shape-<id>`, a shape may get several groups when its triangles
+/// are painted between triangles of other shapes
+pub fn document(
+    width: i32,
+    height: i32,
+    triangles: &[SvgTriangle],
+    group_by_shape: bool,
+) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">\n",
+        width, height
+    );
+    let mut group: Option<ShapeId> = None;
+    for t in triangles.iter() {
+        if group_by_shape && group != Some(t.shape) {
+            if group.is_some() {
+                svg.push_str("</g>\n");
+            }
+            writeln!(svg, "<g class=\"shape-{}\">", t.shape).unwrap();
+            group = Some(t.shape);
+        }
+        polygon(&mut svg, t);
+    }
+    if group.is_some() {
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn point(p: &Point2) -> String {
+    format!("{},{}", number(p.x), number(p.y))
+}
+
+/// Number with at most two decimals, enough for pixels
+fn number(x: f64) -> String {
+    let rounded = (x * 100.).round() / 100.;
+    // avoids "-0"
+    format!("{}", rounded + 0.)
+}
+
+/// Attributes of the paint, alpha goes into a separate attribute
+/// since not all programs understand rgba colors in SVG
+fn paint(svg: &mut String, name: &str, color: &Color) {
+    write!(
+        svg,
+        " {}=\"rgb({},{},{})\"",
+        name, color.r, color.g, color.b
+    )
+    .unwrap();
+    if color.a < 255 {
+        write!(
+            svg,
+            " {}-opacity=\"{}\"",
+            name,
+            number(color.a as f64 / 255.)
+        )
+        .unwrap();
+    }
+}
+
+fn polygon(svg: &mut String, t: &SvgTriangle) {
+    let tri = &t.triangle;
+    write!(
+        svg,
+        "<polygon points=\"{} {} {}\"",
+        point(&tri.p1),
+        point(&tri.p2),
+        point(&tri.p3)
+    )
+    .unwrap();
+    paint(svg, "fill", &t.fill);
+    let stroke = t.material.stroke_color();
+    if t.material.edges_visible && stroke.a > 0 {
+        paint(svg, "stroke", &stroke);
+        write!(svg, " stroke-width=\"{}\"", number(t.material.stroke_width)).unwrap();
+    }
+    svg.push_str("/>\n");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn triangle(shape: ShapeId, material: &Material) -> SvgTriangle<'_> {
+        SvgTriangle {
+            triangle: Triangle2::new(
+                Point2::new(0., 0.),
+                Point2::new(10.5, -0.001),
+                Point2::new(1. / 3., 20.),
+            ),
+            fill: Color::new(255, 0, 0, 128),
+            material,
+            shape,
+        }
+    }
+
+    #[test]
+    fn polygons() {
+        let mut material = Material {
+            stroke: Color::rgb(0, 0, 255),
+            stroke_width: 1.5,
+            ..Material::default()
+        };
+        let svg = document(1200, 800, &[triangle(0, &material)], false);
+        assert_eq!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"1200\" height=\"800\" viewBox=\"0 0 1200 800\">\n\
+             <polygon points=\"0,0 10.5,0 0.33,20\" fill=\"rgb(255,0,0)\" fill-opacity=\"0.5\" \
+             stroke=\"rgb(0,0,255)\" stroke-width=\"1.5\"/>\n\
+             </svg>\n"
+        );
+
+        material.edges_visible = false;
+        let svg = document(1200, 800, &[triangle(0, &material)], false);
+        assert!(!svg.contains("stroke"));
+    }
+
+    #[test]
+    fn groups_follow_the_order_of_painting() {
+        let material = Material::default();
+        let triangles = [
+            triangle(1, &material),
+            triangle(1, &material),
+            triangle(2, &material),
+            triangle(1, &material),
+        ];
+        let svg = document(10, 10, &triangles, true);
+        let groups: Vec<&str> = svg.lines().filter(|l| l.starts_with("<g")).collect();
+        assert_eq!(
+            groups,
+            vec![
+                "<g class=\"shape-1\">",
+                "<g class=\"shape-2\">",
+                "<g class=\"shape-1\">"
+            ]
+        );
+        assert_eq!(svg.matches("</g>").count(), 3);
+        assert_eq!(svg.matches("<polygon").count(), 4);
+    }
+}
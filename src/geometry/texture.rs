@@ -0,0 +1,155 @@
+use wasm_bindgen::prelude::*;
+
+use super::color::Color;
+use super::g2d::Point2;
+
+/// How the color of a texture is taken between its pixels
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TextureFilter {
+    /// Color of the closest pixel
+    Nearest,
+    /// Weighted color of the four closest pixels
+    Bilinear,
+}
+
+/// RGBA image put on triangles.
+/// Texture coordinates (u, v) go from (0, 0) in the top left corner of the image
+/// to (1, 1) in the bottom right one, the image is repeated outside of this range
+#[derive(Debug, Clone, PartialEq)]
+pub struct Texture {
+    width: usize,
+    height: usize,
+    /// 4 bytes per pixel, rows from top to bottom
+    pixels: Vec<u8>,
+    filter: TextureFilter,
+}
+
+impl Texture {
+    /// Returns None if the image is empty or the number of bytes doesn't match its size
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+        filter: TextureFilter,
+    ) -> Option<Self> {
+        if width == 0 || height == 0 || pixels.len() != width * height * 4 {
+            return None;
+        }
+        Some(Texture {
+            width,
+            height,
+            pixels,
+            filter,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: TextureFilter) {
+        self.filter = filter;
+    }
+
+    /// Color of the pixel, coordinates are wrapped around the size of the image
+    fn pixel(&self, x: i64, y: i64) -> Color {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        let idx = (y * self.width + x) * 4;
+        let p = &self.pixels[idx..idx + 4];
+        Color::new(p[0], p[1], p[2], p[3])
+    }
+
+    pub fn sample(&self, uv: &Point2) -> Color {
+        // position in pixels where centers of pixels have integer coordinates
+        let x = uv.x * self.width as f64 - 0.5;
+        let y = uv.y * self.height as f64 - 0.5;
+        match self.filter {
+            TextureFilter::Nearest => self.pixel(x.round() as i64, y.round() as i64),
+            TextureFilter::Bilinear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = mix(self.pixel(x0, y0), self.pixel(x0 + 1, y0), tx);
+                let bottom = mix(self.pixel(x0, y0 + 1), self.pixel(x0 + 1, y0 + 1), tx);
+                mix(top, bottom, ty)
+            }
+        }
+    }
+}
+
+fn mix(a: Color, b: Color, t: f64) -> Color {
+    Color::mix(&[a, b, b], [1. - t, t, 0.])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 2x2 image: black and white in the top row, red and green in the bottom one
+    fn texture(filter: TextureFilter) -> Texture {
+        let pixels = vec![
+            0, 0, 0, 255, 255, 255, 255, 255, //
+            255, 0, 0, 255, 0, 255, 0, 255,
+        ];
+        Texture::new(2, 2, pixels, filter).unwrap()
+    }
+
+    #[test]
+    fn wrong_size() {
+        assert!(Texture::new(2, 2, vec![0; 15], TextureFilter::Nearest).is_none());
+        assert!(Texture::new(0, 0, vec![], TextureFilter::Nearest).is_none());
+    }
+
+    #[test]
+    fn nearest() {
+        let texture = texture(TextureFilter::Nearest);
+        assert_eq!(texture.sample(&Point2::new(0.1, 0.2)), Color::rgb(0, 0, 0));
+        assert_eq!(
+            texture.sample(&Point2::new(0.9, 0.2)),
+            Color::rgb(255, 255, 255)
+        );
+        assert_eq!(
+            texture.sample(&Point2::new(0.1, 0.8)),
+            Color::rgb(255, 0, 0)
+        );
+        // repeated outside of the image
+        assert_eq!(
+            texture.sample(&Point2::new(1.9, -0.2)),
+            Color::rgb(0, 255, 0)
+        );
+    }
+
+    #[test]
+    fn bilinear() {
+        let texture = texture(TextureFilter::Bilinear);
+        // centers of pixels
+        assert_eq!(
+            texture.sample(&Point2::new(0.25, 0.25)),
+            Color::rgb(0, 0, 0)
+        );
+        assert_eq!(
+            texture.sample(&Point2::new(0.75, 0.75)),
+            Color::rgb(0, 255, 0)
+        );
+        // between the top pixels
+        assert_eq!(
+            texture.sample(&Point2::new(0.5, 0.25)),
+            Color::rgb(128, 128, 128)
+        );
+        // center of the image
+        assert_eq!(
+            texture.sample(&Point2::new(0.5, 0.5)),
+            Color::rgb(128, 128, 64)
+        );
+    }
+}
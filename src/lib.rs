@@ -1,11 +1,33 @@
-mod geometry;
+pub mod formats;
+pub mod geometry;
 mod utils;
 
-use std::fmt::format;
+use std::rc::Rc;
 
 use wasm_bindgen::prelude::*;
 
+use crate::formats::gltf::GltfModel;
+use crate::formats::image;
+use crate::formats::obj::ObjModel;
+use crate::formats::ply::PlyModel;
+use crate::formats::scene;
+use crate::formats::stl;
+use crate::geometry::camera::{Camera, Projection};
+use crate::geometry::color::Color;
 use crate::geometry::g3d::{Point3, Vector3};
+use crate::geometry::light::{AmbientLight, DirectionalLight};
+use crate::geometry::material::Material;
+use crate::geometry::raster::FrameBuffer;
+use crate::geometry::texture::{Texture, TextureFilter};
+use crate::geometry::{
+    Animated, Animation, CullMode, OrderingMode, SetOfTriangles, ShadingMode, Shape, ShapeId,
+    Transform,
+};
+
+const WIDTH: i32 = 1200;
+const HEIGHT: i32 = 800;
+const ROTATION_PER_PIXEL: f64 = 0.01;
+const ZOOM_PER_WHEEL_UNIT: f64 = 0.001;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -18,28 +40,25 @@ extern "C" {
     fn alert(s: &str);
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Point {
-    x: i32,
-    y: i32,
+    x: f64,
+    y: f64,
 }
 
 impl Point {
-    pub fn new(x: i32, y: i32) -> Point {
+    pub fn new(x: f64, y: f64) -> Point {
         Point { x, y }
     }
 
-    pub fn rotate(&self, angle: f32) -> Point {
-        let x = self.x as f32;
-        let y = self.y as f32;
-
+    pub fn rotate(&self, angle: f64) -> Point {
         let s = angle.sin();
         let c = angle.cos();
 
-        let x_new = x * c - y * s;
-        let y_new = x * s + y * c;
+        let x_new = self.x * c - self.y * s;
+        let y_new = self.x * s + self.y * c;
 
-        Point::new(x_new.round() as i32, y_new.round() as i32)
+        Point::new(x_new, y_new)
     }
 
     pub fn shift(&self, vector: Point) -> Point {
@@ -50,10 +69,10 @@ impl Point {
 #[derive(Default, Debug)]
 pub struct Polygon {
     points: Vec<Point>,
-    angle: f32,
+    angle: f64,
     center: Point,
     speed_vector: Point,
-    speed_angle: f32,
+    speed_angle: f64,
 }
 
 impl Polygon {
@@ -66,8 +85,8 @@ impl Polygon {
         buf.push(number_of_numbers);
 
         for p in shifted_points {
-            buf.push(p.x);
-            buf.push(p.y);
+            buf.push(p.x.round() as i32);
+            buf.push(p.y.round() as i32);
         }
         number_of_numbers + 1
     }
@@ -81,62 +100,381 @@ impl Polygon {
 #[wasm_bindgen]
 pub struct Scene {
     scene_tmp: geometry::SceneTmp,
+    frame: Vec<i32>,
+    pixels: FrameBuffer,
 }
 
 #[wasm_bindgen]
 impl Scene {
     pub fn new() -> Self {
+        utils::set_panic_hook();
+
+        let uvs = [
+            geometry::g2d::Point2::new(0., 0.),
+            geometry::g2d::Point2::new(1., 0.),
+            geometry::g2d::Point2::new(0.25, 0.75),
+        ];
         let tri1 = geometry::g3d::Triagnle3::new(
-            geometry::g3d::Point3::new(200, 200, 0),
-            geometry::g3d::Point3::new(600, 200, 0),
-            geometry::g3d::Point3::new(300, 500, 0),
-        );
+            geometry::g3d::Point3::new(200., 200., 0.),
+            geometry::g3d::Point3::new(600., 200., 0.),
+            geometry::g3d::Point3::new(300., 500., 0.),
+        )
+        .with_uvs(uvs);
         let tri2 = geometry::g3d::Triagnle3::new(
-            geometry::g3d::Point3::new(200, 200, 50),
-            geometry::g3d::Point3::new(600, 200, 50),
-            geometry::g3d::Point3::new(300, 500, 50),
-        );
+            geometry::g3d::Point3::new(200., 200., 50.),
+            geometry::g3d::Point3::new(600., 200., 50.),
+            geometry::g3d::Point3::new(300., 500., 50.),
+        )
+        .with_uvs(uvs);
 
         // let tri1 = geometry::g3d::Triagnle3::new(
-        //     geometry::g3d::Point3::new(200, 200, 500),
-        //     geometry::g3d::Point3::new(600, 200, 500),
-        //     geometry::g3d::Point3::new(300, 500, 500),
+        //     geometry::g3d::Point3::new(200., 200., 500.),
+        //     geometry::g3d::Point3::new(600., 200., 500.),
+        //     geometry::g3d::Point3::new(300., 500., 500.),
         // );
-        
+
         // let tri2 = geometry::g3d::Triagnle3::new(
-        //     geometry::g3d::Point3::new(200, 200, 505),
-        //     geometry::g3d::Point3::new(600, 200, 505),
-        //     geometry::g3d::Point3::new(300, 400, 600),
+        //     geometry::g3d::Point3::new(200., 200., 505.),
+        //     geometry::g3d::Point3::new(600., 200., 505.),
+        //     geometry::g3d::Point3::new(300., 400., 600.),
         // );
 
         // let tri3 = geometry::g3d::Triagnle3::new(
-        //     geometry::g3d::Point3::new(600, 205, 505),
-        //     geometry::g3d::Point3::new(305, 500, 505),
-        //     geometry::g3d::Point3::new(295, 395, 600),
+        //     geometry::g3d::Point3::new(600., 205., 505.),
+        //     geometry::g3d::Point3::new(305., 500., 505.),
+        //     geometry::g3d::Point3::new(295., 395., 600.),
         // );
 
         // let tri4 = geometry::g3d::Triagnle3::new(
-        //     geometry::g3d::Point3::new(200, 205, 505),
-        //     geometry::g3d::Point3::new(295, 500, 505),
-        //     geometry::g3d::Point3::new(305, 395, 600),
+        //     geometry::g3d::Point3::new(200., 205., 505.),
+        //     geometry::g3d::Point3::new(295., 500., 505.),
+        //     geometry::g3d::Point3::new(305., 395., 600.),
         // );
 
-        let shapes = geometry::SetOfTriangles::new(
-            vec![tri1, tri2],
-            Point3::new(0, 0, 0),
-            Vector3::new(100, 100, 10),
-            0.01,
-            Vector3::new(0, 0, 0)
+        let shapes = geometry::SetOfTriangles::new(vec![tri1, tri2]);
+        let mut camera = Camera::new(
+            Point3::new(WIDTH as f64 / 2., HEIGHT as f64 / 2., 1000.),
+            Point3::new(WIDTH as f64 / 2., HEIGHT as f64 / 2., 0.),
+            Vector3::new(0., -1., 0.),
+            WIDTH,
+            HEIGHT,
         );
-        let scene_tmp = geometry::SceneTmp::new(shapes);
-        Self { scene_tmp }
+        // plane z = 0 is shown in the scale 1:1
+        camera.set_fov(2. * (HEIGHT as f64 / 2. / 1000.).atan());
+        let mut scene_tmp = geometry::SceneTmp::new(camera);
+        scene_tmp.add_shape(Box::new(Animated::new(
+            shapes,
+            Transform::new(
+                Point3::new(0., 0., 0.),
+                Vector3::new(100., 100., 10.),
+                0.01,
+                Vector3::new(0., 0., 0.),
+            ),
+            Animation::new(0.01, 0.),
+        )));
+        let center = scene_tmp.center();
+        scene_tmp.camera_mut().set_target(center);
+        Self::with_scene(scene_tmp)
+    }
+
+    /// Scene saved by `to_json`, see `formats::scene` for the schema.
+    /// Returns the description of the error if the text is not a valid scene
+    pub fn from_json(json: &str) -> Result<Scene, JsValue> {
+        utils::set_panic_hook();
+        let scene_tmp = scene::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Self::with_scene(scene_tmp))
+    }
+
+    pub fn to_json(&self) -> String {
+        scene::to_json(&self.scene_tmp).to_string()
     }
 
     pub fn tick(&mut self) {
         self.scene_tmp.step();
     }
 
-    pub fn render(&self) -> *const i32 {
-        self.scene_tmp.render()
+    /// Renders current frame into the buffer owned by the scene and returns pointer to it.
+    /// The data stays valid until the next call of `render`; `tick` doesn't touch it.
+    pub fn render(&mut self) -> *const i32 {
+        self.scene_tmp.render(&mut self.frame);
+        self.frame.as_ptr()
+    }
+
+    /// SVG document of the current frame, the same triangles as the ones given by `render`.
+    /// With `group_by_shape` triangles of every shape are wrapped into groups
+    /// with the class `shape-<id>`
+    pub fn to_svg(&self, group_by_shape: bool) -> String {
+        self.scene_tmp.to_svg(group_by_shape)
+    }
+
+    /// Size of the frames given by `render` and `rasterize`, scenes loaded by `from_json`
+    /// keep the size of the saved camera
+    pub fn viewport_width(&self) -> i32 {
+        self.scene_tmp.camera().viewport().0
+    }
+
+    pub fn viewport_height(&self) -> i32 {
+        self.scene_tmp.camera().viewport().1
+    }
+
+    pub fn is_orthographic(&self) -> bool {
+        self.scene_tmp.camera().projection() == Projection::Orthographic
+    }
+
+    pub fn set_orthographic(&mut self, orthographic: bool) {
+        let projection = if orthographic {
+            Projection::Orthographic
+        } else {
+            Projection::Perspective
+        };
+        self.scene_tmp.camera_mut().set_projection(projection);
+    }
+
+    pub fn is_bsp_ordering(&self) -> bool {
+        self.scene_tmp.ordering_mode() == OrderingMode::Bsp
+    }
+
+    /// Switches between ordering by the BSP tree and by the graph of overlapping triangles
+    pub fn set_bsp_ordering(&mut self, bsp: bool) {
+        let mode = if bsp {
+            OrderingMode::Bsp
+        } else {
+            OrderingMode::Graph
+        };
+        self.scene_tmp.set_ordering_mode(mode);
+    }
+
+    pub fn shading_mode(&self) -> ShadingMode {
+        self.scene_tmp.shading_mode()
+    }
+
+    /// Shading of the rasterized frame, the painted frame always uses the flat shading
+    pub fn set_shading_mode(&mut self, mode: ShadingMode) {
+        self.scene_tmp.set_shading_mode(mode);
+    }
+
+    pub fn set_ambient_light(&mut self, r: u8, g: u8, b: u8, intensity: f64) {
+        self.scene_tmp.lighting_mut().ambient = AmbientLight::new(Color::rgb(r, g, b), intensity);
+    }
+
+    /// Adds a light shining along the direction (x, y, z) in the world coordinates
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_directional_light(
+        &mut self,
+        x: f64,
+        y: f64,
+        z: f64,
+        r: u8,
+        g: u8,
+        b: u8,
+        intensity: f64,
+    ) {
+        let light = DirectionalLight::new(Vector3::new(x, y, z), Color::rgb(r, g, b), intensity);
+        self.scene_tmp.lighting_mut().directional.push(light);
+    }
+
+    pub fn clear_directional_lights(&mut self) {
+        self.scene_tmp.lighting_mut().directional.clear();
+    }
+
+    /// Number of values describing one triangle in the buffer returned by `render`
+    pub fn render_stride() -> usize {
+        geometry::RENDER_STRIDE
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn set_fill_color(&mut self, id: ShapeId, r: u8, g: u8, b: u8, a: u8) -> bool {
+        self.update_material(id, |m| m.fill = Color::new(r, g, b, a))
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn set_stroke_color(&mut self, id: ShapeId, r: u8, g: u8, b: u8, a: u8) -> bool {
+        self.update_material(id, |m| m.stroke = Color::new(r, g, b, a))
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn set_opacity(&mut self, id: ShapeId, opacity: f64) -> bool {
+        self.update_material(id, |m| m.opacity = opacity)
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn set_stroke_width(&mut self, id: ShapeId, width: f64) -> bool {
+        self.update_material(id, |m| m.stroke_width = width)
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn set_edges_visible(&mut self, id: ShapeId, visible: bool) -> bool {
+        self.update_material(id, |m| m.edges_visible = visible)
+    }
+
+    /// Puts the RGBA image on the shape in the rasterized frame, `pixels` go row by row
+    /// from the top. Returns false if there is no shape with such id
+    /// or the number of bytes doesn't match the size of the image
+    pub fn set_texture(
+        &mut self,
+        id: ShapeId,
+        width: usize,
+        height: usize,
+        pixels: Vec<u8>,
+        filter: TextureFilter,
+    ) -> bool {
+        match Texture::new(width, height, pixels, filter) {
+            Some(texture) => self.update_material(id, |m| m.texture = Some(Rc::new(texture))),
+            None => false,
+        }
+    }
+
+    /// Returns false if there is no shape with such id
+    pub fn remove_texture(&mut self, id: ShapeId) -> bool {
+        self.update_material(id, |m| m.texture = None)
+    }
+
+    /// Returns undefined if there is no shape with such id
+    pub fn cull_mode(&self, id: ShapeId) -> Option<CullMode> {
+        self.scene_tmp.cull_mode(id)
     }
+
+    /// Sets which sides of triangles of the shape are not drawn,
+    /// returns false if there is no shape with such id
+    pub fn set_cull_mode(&mut self, id: ShapeId, cull_mode: CullMode) -> bool {
+        self.scene_tmp.set_cull_mode(id, cull_mode)
+    }
+
+    /// Adds the Wavefront OBJ model as one shape, `mtl` is the content
+    /// of its material library. Faces without `vn` normals get smooth normals.
+    /// Returns the id of the shape or the description of the error
+    pub fn add_obj(&mut self, obj: &str, mtl: Option<String>) -> Result<ShapeId, JsValue> {
+        let model =
+            ObjModel::parse(obj, mtl.as_deref()).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(self.scene_tmp.add_shape(smooth(&model)))
+    }
+
+    /// Adds the ASCII or binary STL model as one shape with the normals of its facets.
+    /// Returns the id of the shape or the description of the error
+    pub fn add_stl(&mut self, bytes: &[u8]) -> Result<ShapeId, JsValue> {
+        let triangles = stl::parse(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let shape = SetOfTriangles::new(triangles);
+        Ok(self.scene_tmp.add_shape(Box::new(shape)))
+    }
+
+    /// Adds the ASCII or binary little endian PLY model as one shape with colors of its vertices.
+    /// Vertices without normals get smooth normals, point clouds without faces are rejected.
+    /// Returns the id of the shape or the description of the error
+    pub fn add_ply(&mut self, bytes: &[u8]) -> Result<ShapeId, JsValue> {
+        let model = PlyModel::parse(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(self.scene_tmp.add_shape(smooth(&model)))
+    }
+
+    /// Adds every mesh of the glTF binary model as a separate shape
+    /// filled with the base color of its material, meshes without normals get smooth normals.
+    /// Returns ids of the shapes or the description of the error
+    pub fn add_glb(&mut self, bytes: &[u8]) -> Result<Vec<ShapeId>, JsValue> {
+        let model = GltfModel::parse_glb(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(model
+            .meshes
+            .into_iter()
+            .map(|gltf_mesh| {
+                let color = gltf_mesh.color;
+                let id = self.scene_tmp.add_shape(smooth(&gltf_mesh.mesh));
+                self.update_material(id, |material| material.fill = color);
+                id
+            })
+            .collect())
+    }
+
+    /// Removes the shape from the scene, returns false if there is no shape with such id
+    pub fn remove_shape(&mut self, id: ShapeId) -> bool {
+        self.scene_tmp.remove_shape(id).is_some()
+    }
+
+    pub fn shape_ids(&self) -> Vec<ShapeId> {
+        self.scene_tmp.shape_ids()
+    }
+
+    /// Orbits the camera around its target, `dx` and `dy` are pointer movement in pixels
+    pub fn rotate_camera(&mut self, dx: f64, dy: f64) {
+        self.scene_tmp
+            .camera_mut()
+            .orbit(-dx * ROTATION_PER_PIXEL, dy * ROTATION_PER_PIXEL);
+    }
+
+    /// Moves the camera closer to its target or further away, `delta` is the wheel delta
+    pub fn zoom_camera(&mut self, delta: f64) {
+        self.scene_tmp
+            .camera_mut()
+            .zoom((delta * ZOOM_PER_WHEEL_UNIT).exp());
+    }
+
+    /// Moves the camera parallel to the screen, `dx` and `dy` are pointer movement in pixels
+    pub fn pan_camera(&mut self, dx: f64, dy: f64) {
+        self.scene_tmp.camera_mut().pan(dx, dy);
+    }
+
+    /// Points the camera to the center of the scene
+    pub fn center_camera(&mut self) {
+        let center = self.scene_tmp.center();
+        self.scene_tmp.camera_mut().set_target(center);
+    }
+
+    /// Number of `i32` values in the buffer returned by the last `render`
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+
+    /// Draws current frame with the depth buffer into the RGBA pixel buffer owned by the scene
+    /// and returns pointer to it. The buffer has `pixels_len` bytes, 4 bytes per pixel,
+    /// rows go from top to bottom, so it can be used for `ImageData` directly
+    pub fn rasterize(&mut self) -> *const u8 {
+        self.scene_tmp.rasterize(&mut self.pixels);
+        self.pixels.pixels().as_ptr()
+    }
+
+    /// PNG image of the current frame drawn by the rasterizer, the background is transparent
+    pub fn png(&mut self) -> Vec<u8> {
+        self.scene_tmp.rasterize(&mut self.pixels);
+        image::png(&self.pixels)
+    }
+
+    /// PPM image of the current frame drawn by the rasterizer over the background color
+    pub fn ppm(&mut self, r: u8, g: u8, b: u8) -> Vec<u8> {
+        self.scene_tmp.rasterize(&mut self.pixels);
+        image::ppm(&self.pixels, Color::rgb(r, g, b))
+    }
+
+    pub fn pixels_len(&self) -> usize {
+        self.pixels.pixels().len()
+    }
+}
+
+impl Scene {
+    fn with_scene(scene_tmp: geometry::SceneTmp) -> Self {
+        let (width, height) = scene_tmp.camera().viewport();
+        Self {
+            scene_tmp,
+            frame: Vec::new(),
+            pixels: FrameBuffer::new(width as usize, height as usize),
+        }
+    }
+
+    fn update_material<F: FnOnce(&mut Material)>(&mut self, id: ShapeId, update: F) -> bool {
+        match self.scene_tmp.material_mut(id) {
+            Some(material) => {
+                update(material);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Triangles of the model where the ones without vertex normals get normals averaged
+/// over shared vertices, so Gouraud and Phong shading of curved models isn't faceted
+fn smooth(model: &dyn Shape) -> Box<dyn Shape> {
+    Box::new(SetOfTriangles::new(model.approximate()).with_smooth_normals())
 }
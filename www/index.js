@@ -3,30 +3,38 @@ import { memory } from "wasm-package/playing_with_canvas_bg";
 
 
 const canvas = document.getElementById("playing-canvas");
-const HEIGNT = 800
-const WIDTH = 1200
-
-const BORDER_COLOR = "#F0000F"
-const FILL_COLOR = "#00F00F"
-
-canvas.height = HEIGNT
-canvas.width = WIDTH
+// the size of the canvas follows the viewport of the scene, loaded scenes may have another one
+var HEIGNT = 800
+var WIDTH = 1200
+
+// x1, y1, x2, y2, x3, y3, fill rgba, stroke rgba, stroke width in hundredths of a pixel
+const TRIANGLE_LEN = wasm.Scene.render_stride()
+
+function resize_canvas(scene) {
+    WIDTH = scene.viewport_width();
+    HEIGNT = scene.viewport_height();
+    canvas.height = HEIGNT
+    canvas.width = WIDTH
+}
 
 const ctx = canvas.getContext('2d');
 
-ctx.strokeStyle = BORDER_COLOR;
-ctx.fillStyle = FILL_COLOR;
-
 function draw_scene(scene) {
+    // the view has to be created after render: the buffer may be reallocated
+    // and wasm memory may grow, both invalidate previously created views
     const bufPtr = scene.render();
-    const oneElelemtArray = new Int32Array(memory.buffer, bufPtr, 1);
-    const numTriangles = oneElelemtArray[0];
-    var buf = new Int32Array(memory.buffer, bufPtr, numTriangles*6+1);
+    const buf = new Int32Array(memory.buffer, bufPtr, scene.frame_len());
+    const numTriangles = buf[0];
 
     for(var idx = 0; idx < numTriangles; idx++) {
+        const start = idx*TRIANGLE_LEN + 1;
+        const fill = buf.subarray(start + 6, start + 10);
+        const stroke = buf.subarray(start + 10, start + 14);
+        const strokeWidth = buf[start + 14] / 100;
+        ctx.fillStyle = `rgba(${fill[0]}, ${fill[1]}, ${fill[2]}, ${fill[3] / 255})`;
         ctx.beginPath();
         for(var jdx = 0; jdx < 3; jdx++) {
-            const x_idx = idx*6+1 + jdx*2;
+            const x_idx = start + jdx*2;
             const y_idx = x_idx + 1;
             const x = buf[x_idx];
             const y = buf[y_idx];
@@ -39,11 +47,174 @@ function draw_scene(scene) {
         } 
         ctx.closePath();
         ctx.fill();
-        ctx.stroke();
+        if (strokeWidth > 0) {
+            ctx.strokeStyle = `rgba(${stroke[0]}, ${stroke[1]}, ${stroke[2]}, ${stroke[3] / 255})`;
+            ctx.lineWidth = strokeWidth;
+            ctx.stroke();
+        }
     }
 }
 
+function rasterize_scene(scene) {
+    const pixelsPtr = scene.rasterize();
+    const pixels = new Uint8ClampedArray(memory.buffer, pixelsPtr, scene.pixels_len());
+    ctx.putImageData(new ImageData(pixels, WIDTH, HEIGNT), 0, 0);
+}
+
+// RGBA image with the given number of cells, any image data can be used as a texture
+function checkerboard(columns, rows) {
+    const texture = new ImageData(columns, rows);
+    for (var y = 0; y < rows; y++) {
+        for (var x = 0; x < columns; x++) {
+            const idx = (y * columns + x) * 4;
+            const light = (x + y) % 2 == 0;
+            texture.data.set(light ? [255, 220, 120, 255] : [60, 40, 160, 255], idx);
+        }
+    }
+    return texture;
+}
+
+function download(name, blob) {
+    const link = document.createElement("a");
+    link.href = URL.createObjectURL(blob);
+    link.download = name;
+    link.click();
+    URL.revokeObjectURL(link.href);
+}
+
 var scene = wasm.Scene.new();
+resize_canvas(scene);
+
+// "o" switches between perspective and orthographic projections,
+// "b" switches between BSP tree and overlap graph ordering,
+// "r" switches between painting triangles and the z-buffer rasterizer,
+// "c" points the camera to the center of the scene,
+// "f" switches culling of all shapes between none, back faces and front faces,
+// "s" switches shading of the rasterized frame between flat, Gouraud and Phong,
+// "t" switches the texture of all shapes between none, nearest and bilinear filtering,
+// "v" saves the painted frame as SVG with triangles grouped by shapes,
+// "p" saves the rasterized frame as PNG,
+// "j" saves the scene as JSON, dropping a saved file replaces the scene
+var orthographic = false;
+var bsp = false;
+var rasterize = false;
+const CULL_MODES = [wasm.CullMode.None, wasm.CullMode.Back, wasm.CullMode.Front];
+var cullMode = 0;
+const SHADING_MODES = [wasm.ShadingMode.Flat, wasm.ShadingMode.Gouraud, wasm.ShadingMode.Phong];
+var shadingMode = 0;
+const TEXTURE_FILTERS = [null, wasm.TextureFilter.Nearest, wasm.TextureFilter.Bilinear];
+var textureFilter = 0;
+
+// toggles take the state of the loaded scene, textures are not saved into JSON
+function reset_toggles(scene) {
+    orthographic = scene.is_orthographic();
+    bsp = scene.is_bsp_ordering();
+    const ids = scene.shape_ids();
+    cullMode = ids.length > 0 ? Math.max(CULL_MODES.indexOf(scene.cull_mode(ids[0])), 0) : 0;
+    shadingMode = SHADING_MODES.indexOf(scene.shading_mode());
+    textureFilter = 0;
+}
+
+document.addEventListener("keydown", event => {
+    if (event.key == "o") {
+        orthographic = !orthographic;
+        scene.set_orthographic(orthographic);
+    } else if (event.key == "b") {
+        bsp = !bsp;
+        scene.set_bsp_ordering(bsp);
+    } else if (event.key == "r") {
+        rasterize = !rasterize;
+    } else if (event.key == "c") {
+        scene.center_camera();
+    } else if (event.key == "f") {
+        cullMode = (cullMode + 1) % CULL_MODES.length;
+        for (const id of scene.shape_ids()) {
+            scene.set_cull_mode(id, CULL_MODES[cullMode]);
+        }
+    } else if (event.key == "s") {
+        shadingMode = (shadingMode + 1) % SHADING_MODES.length;
+        scene.set_shading_mode(SHADING_MODES[shadingMode]);
+    } else if (event.key == "t") {
+        textureFilter = (textureFilter + 1) % TEXTURE_FILTERS.length;
+        const filter = TEXTURE_FILTERS[textureFilter];
+        const texture = checkerboard(8, 8);
+        for (const id of scene.shape_ids()) {
+            if (filter === null) {
+                scene.remove_texture(id);
+            } else {
+                scene.set_texture(id, texture.width, texture.height, texture.data, filter);
+            }
+        }
+    } else if (event.key == "v") {
+        download("frame.svg", new Blob([scene.to_svg(true)], { type: "image/svg+xml" }));
+    } else if (event.key == "p") {
+        download("frame.png", new Blob([scene.png()], { type: "image/png" }));
+    } else if (event.key == "j") {
+        download("scene.json", new Blob([scene.to_json()], { type: "application/json" }));
+    }
+});
+
+// drag rotates the camera around the scene, shift + drag pans it and wheel zooms
+var dragging = false;
+canvas.addEventListener("pointerdown", event => {
+    dragging = true;
+    canvas.setPointerCapture(event.pointerId);
+});
+canvas.addEventListener("pointerup", event => {
+    dragging = false;
+    canvas.releasePointerCapture(event.pointerId);
+});
+canvas.addEventListener("pointermove", event => {
+    if (!dragging) {
+        return;
+    }
+    if (event.shiftKey) {
+        scene.pan_camera(event.movementX, event.movementY);
+    } else {
+        scene.rotate_camera(event.movementX, event.movementY);
+    }
+});
+canvas.addEventListener("wheel", event => {
+    event.preventDefault();
+    scene.zoom_camera(event.deltaY);
+}, { passive: false });
+
+// dropped STL, PLY, glTF binary and OBJ files are added to the scene
+canvas.addEventListener("dragover", event => {
+    event.preventDefault();
+});
+canvas.addEventListener("drop", async event => {
+    event.preventDefault();
+    for (const file of event.dataTransfer.files) {
+        const name = file.name.toLowerCase();
+        try {
+            if (name.endsWith(".stl")) {
+                scene.add_stl(new Uint8Array(await file.arrayBuffer()));
+            } else if (name.endsWith(".ply")) {
+                scene.add_ply(new Uint8Array(await file.arrayBuffer()));
+            } else if (name.endsWith(".glb")) {
+                scene.add_glb(new Uint8Array(await file.arrayBuffer()));
+            } else if (name.endsWith(".json")) {
+                // the saved camera is kept, so the scene is not centered
+                const loaded = wasm.Scene.from_json(await file.text());
+                scene.free();
+                scene = loaded;
+                resize_canvas(scene);
+                reset_toggles(scene);
+                continue;
+            } else if (name.endsWith(".obj")) {
+                scene.add_obj(await file.text());
+            } else {
+                console.warn(`${file.name}: only STL, PLY, GLB, OBJ and scene JSON files are supported`);
+                continue;
+            }
+        } catch (error) {
+            console.error(`${file.name}: ${error}`);
+            continue;
+        }
+        scene.center_camera();
+    }
+});
 
 function sleep(delay) {
     var start = new Date().getTime();
@@ -54,7 +225,11 @@ const renderLoop = () => {
 
     ctx.clearRect(0, 0, WIDTH, HEIGNT);
     // sleep(1000);
-    draw_scene(scene);
+    if (rasterize) {
+        rasterize_scene(scene);
+    } else {
+        draw_scene(scene);
+    }
     scene.tick();
 
     requestAnimationFrame(renderLoop);